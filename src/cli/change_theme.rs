@@ -1,9 +1,17 @@
-use crate::config::{Config, ThemeConfig};
+use crate::config::{Config, Hook, OnFailure, ThemeConfig};
 use crate::templates::renderer;
 use crate::theme::Theme;
-use crate::utils::history;
-use anyhow::{Context, Result};
-use std::process::{Command, Stdio};
+use crate::utils::export::{self, ExportFormat};
+use crate::utils::palette::{LabeledColors, Swatch};
+use crate::utils::{ansi, history, palette, paths, sequences};
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// run a shell command with sh -c, blocking until it exits
 fn run_sh(command: &str) -> Result<()> {
@@ -39,16 +47,13 @@ fn spawn_sh(command: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn change_theme(
-    theme: &Theme,
+/// kill the old wallpaper daemon and set the new wallpaper, using the per-theme
+/// commands when the theme has them
+fn set_wallpaper(
+    wallpaper: &str,
     config: &Config,
     named: Option<(&str, &ThemeConfig)>,
 ) -> Result<()> {
-    let wallpaper = &theme.wallpaper;
-    println!("Wallpaper: {}", wallpaper.display());
-
-    let wallpaper_str = wallpaper.to_string_lossy().to_string();
-
     // use per-theme override if present, else fall back to global config
     let kill_cmd = named
         .and_then(|(_, tc)| tc.wallpaper_kill.as_deref())
@@ -67,44 +72,291 @@ pub fn change_theme(
     // set the new wallpaper if configured
     if let Some(wp_cmd) = wp_cmd {
         // only need {{wallpaper}} for this substitution, build a minimal vars map
-        let mut vars = std::collections::HashMap::new();
-        vars.insert("wallpaper".to_string(), wallpaper_str.clone());
-        let resolved = renderer::substitute(wp_cmd, &vars);
+        let mut vars = HashMap::new();
+        vars.insert("wallpaper".to_string(), wallpaper.to_string());
+        let resolved =
+            renderer::substitute(wp_cmd, &vars).context("Invalid wallpaper_command")?;
         spawn_sh(&resolved).context("wallpaper_command failed")?;
         println!("Ran wallpaper_command: {resolved}");
     }
 
-    // extract palette once if hooks or templates need it
-    let palette = if !config.hooks.is_empty() || !config.templates.is_empty() {
-        Some(theme.palette(config.palette_size)?)
+    Ok(())
+}
+
+/// wait for `child` to exit, killing its process group once `timeout` has
+/// passed. None if it had to be killed
+fn wait_timeout(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if start.elapsed() >= timeout {
+            // the hook is sh -c, so killing only the child would leave whatever
+            // it started running. the child leads its own group, see run_hook
+            let pgid = child.id() as libc::pid_t;
+            // SAFETY: kill has no memory preconditions, a stale pid just fails
+            unsafe { libc::kill(-pgid, libc::SIGKILL) };
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// run a hook with `env` added to its environment. hooks in the background only
+/// fail when they cant be started, blocking ones also when they exit non-zero
+/// or run past their timeout
+fn run_hook(hook: &Hook, index: usize, command: &str, env: &[(String, String)]) -> Result<()> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0);
+
+    let log = if hook.stdout || hook.stderr {
+        let dir = paths::cache_dir()?.join("hooks");
+        fs::create_dir_all(&dir).context("Failed to create hook log dir")?;
+        let path = dir.join(hook.log_name(index)?);
+        let file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        if hook.stdout {
+            cmd.stdout(file.try_clone()?);
+        }
+        if hook.stderr {
+            cmd.stderr(file);
+        }
+        Some(path)
+    } else {
+        None
+    };
+
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to spawn: {command}"))?;
+    if !hook.blocking() {
+        return Ok(());
+    }
+
+    let status = match hook.timeout {
+        Some(timeout) => wait_timeout(&mut child, timeout)?,
+        None => Some(child.wait()?),
+    };
+    let failure = match status {
+        Some(status) if status.success() => return Ok(()),
+        Some(status) => format!("exited with {status}"),
+        None => format!("timed out after {:?}", hook.timeout.unwrap_or_default()),
+    };
+    match log {
+        Some(log) => bail!("{failure}: {command}, see {}", log.display()),
+        None => bail!("{failure}: {command}"),
+    }
+}
+
+/// the environment hooks run with, every template variable plus the theme name
+/// and the palette file
+fn hook_env(
+    vars: &HashMap<String, String>,
+    theme_name: String,
+    palette_file: &Path,
+) -> Vec<(String, String)> {
+    let mut env = renderer::env_vars(vars);
+    env.push(("CHLOROPHYLL_THEME".to_string(), theme_name));
+    env.push((
+        "CHLOROPHYLL_PALETTE".to_string(),
+        palette_file.to_string_lossy().to_string(),
+    ));
+    env
+}
+
+/// run every hook in order. a failing hook only stops the rest when it has
+/// on_failure = "abort"
+fn run_hooks(
+    hooks: &[Hook],
+    vars: &HashMap<String, String>,
+    env: &[(String, String)],
+) -> Result<()> {
+    for (i, hook) in hooks.iter().enumerate() {
+        let ran = renderer::substitute(&hook.command, vars)
+            .with_context(|| format!("Invalid hook '{}'", hook.command))
+            .and_then(|resolved| {
+                run_hook(hook, i, &resolved, env)?;
+                Ok(resolved)
+            });
+        match ran {
+            Ok(resolved) => println!("Ran hook: {resolved}"),
+            Err(e) if hook.on_failure == OnFailure::Abort => {
+                return Err(e.context("A hook with on_failure = \"abort\" failed"));
+            }
+            Err(e) => eprintln!("warning: hook failed: {e:#}"),
+        }
+    }
+    Ok(())
+}
+
+/// the named theme, or the wallpaper's file name without its extension
+fn theme_name(theme: &Theme, named: Option<(&str, &ThemeConfig)>) -> String {
+    match named {
+        Some((name, _)) => name.to_string(),
+        None => theme
+            .wallpaper
+            .as_ref()
+            .and_then(|w| w.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// write the palette as json to the cache dir for hooks to read, same as
+/// `chlorophyll export --format json`
+fn write_palette_file(swatches: &[Swatch], labels: &LabeledColors, name: &str) -> Result<PathBuf> {
+    let path = paths::cache_dir()?.join("palette.json");
+    let json = export::export(ExportFormat::Json, name, swatches, labels);
+    // written atomically, a hook from the last theme change could still be reading it
+    renderer::write_atomic(&path, json).context("Failed to write palette file")?;
+    Ok(path)
+}
+
+pub fn change_theme(
+    theme: &Theme,
+    config: &Config,
+    named: Option<(&str, &ThemeConfig)>,
+) -> Result<()> {
+    // themes with only an imported palette leave the current wallpaper alone
+    let wallpaper_str = match theme.wallpaper {
+        Some(ref wallpaper) => {
+            println!("Wallpaper: {}", wallpaper.display());
+            let wallpaper_str = wallpaper.to_string_lossy().to_string();
+            set_wallpaper(&wallpaper_str, config, named)?;
+            wallpaper_str
+        }
+        None => String::new(),
+    };
+
+    // extract palette and labels once if hooks, templates or sequences need them
+    let extracted = if !config.hooks.is_empty()
+        || !config.templates.is_empty()
+        || config.terminal_sequences
+    {
+        let swatches = theme.palette(&config.palette_options())?;
+        let labels = palette::assign_labels(&swatches, &config.label_options());
+        Some((swatches, labels))
     } else {
         None
     };
 
     // run post-theme-change hooks with full template vars (colors + wallpaper)
-    if let Some(ref palette) = palette
+    if let Some((ref swatches, ref labels)) = extracted
         && !config.hooks.is_empty()
     {
-        let vars = renderer::build_variables(palette, &wallpaper_str);
+        let vars = renderer::build_variables(swatches, labels, &wallpaper_str);
 
-        for hook in &config.hooks {
-            let resolved = renderer::substitute(&hook.command, &vars);
-            match spawn_sh(&resolved) {
-                Ok(()) => println!("Ran hook: {resolved}"),
-                Err(e) => eprintln!("warning: hook failed: {e}"),
-            }
-        }
+        // the same variables go into the environment, so scripts dont need a
+        // {{..}} for every color they use
+        let theme_name = theme_name(theme, named);
+        let palette_file = write_palette_file(swatches, labels, &theme_name)?;
+        let env = hook_env(&vars, theme_name, &palette_file);
+
+        run_hooks(&config.hooks, &vars, &env)?;
     }
 
     if let Some((name, _)) = named {
         history::save_custom_theme(name)?;
-    } else {
-        history::save_wallpaper(&theme.wallpaper)?;
+    } else if let Some(ref wallpaper) = theme.wallpaper {
+        history::save_wallpaper(wallpaper)?;
     }
 
-    if let Some(ref palette) = palette {
-        renderer::render_templates(palette, &theme.wallpaper, &config.templates)?;
+    if let Some((ref swatches, ref labels)) = extracted {
+        renderer::render_templates(
+            swatches,
+            labels,
+            &wallpaper_str,
+            &config.templates,
+            config.strict_templates,
+        )?;
+
+        if config.terminal_sequences {
+            let ansi = ansi::ansi_colors(swatches, labels);
+            let recolored = sequences::apply(&ansi, labels)?;
+            println!("Recolored {recolored} open terminals");
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(toml: &str) -> Hook {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn blocking_hook_failures() {
+        let failing = hook("command = \"x\"\nwait = true");
+        let err = run_hook(&failing, 0, "exit 3", &[]).unwrap_err();
+        assert!(err.to_string().contains("exited with"), "{err}");
+        assert!(run_hook(&failing, 0, "true", &[]).is_ok());
+        // in the background only a failure to start counts
+        let background = hook("command = \"x\"");
+        assert!(run_hook(&background, 0, "exit 3", &[]).is_ok());
+    }
+
+    #[test]
+    fn hook_timeout_kills_the_group() {
+        let pid_file =
+            std::env::temp_dir().join(format!("chlorophyll-hook-pid-{}", std::process::id()));
+        let slow = hook("command = \"x\"\ntimeout = \"200ms\"");
+        // the sleep is a grandchild, it has to go down with the shell
+        let command = format!("sleep 5 & echo $! > {}; wait", pid_file.display());
+        let err = run_hook(&slow, 0, &command, &[]).unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{err}");
+
+        let pid = fs::read_to_string(&pid_file).unwrap();
+        fs::remove_file(&pid_file).unwrap();
+        // gone, or a zombie waiting for init to reap it
+        let running = || {
+            fs::read_to_string(format!("/proc/{}/stat", pid.trim()))
+                .is_ok_and(|stat| !stat.contains(") Z "))
+        };
+        let start = Instant::now();
+        while running() && start.elapsed() < Duration::from_secs(1) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!running(), "sleep outlived its hook");
+    }
+
+    #[test]
+    fn hooks_see_the_env() {
+        let mut vars = HashMap::new();
+        vars.insert("mode".to_string(), "dark".to_string());
+        let env = hook_env(&vars, "forest".to_string(), Path::new("/tmp/palette.json"));
+        let out = Command::new("sh")
+            .arg("-c")
+            .arg("echo $CHLOROPHYLL_THEME $CHLOROPHYLL_PALETTE $CHLOROPHYLL_MODE")
+            .envs(env)
+            .output()
+            .unwrap();
+        let out = String::from_utf8(out.stdout).unwrap();
+        assert_eq!(out.trim(), "forest /tmp/palette.json dark");
+    }
+
+    #[test]
+    fn abort_stops_the_theme() {
+        let vars = HashMap::new();
+        let hooks = [
+            hook("command = \"exit 1\""),
+            hook("command = \"exit 1\"\non_failure = \"abort\""),
+            hook("command = \"true\""),
+        ];
+        let err = run_hooks(&hooks, &vars, &[]).unwrap_err();
+        assert!(err.to_string().contains("abort"), "{err}");
+        // warn only warns
+        assert!(run_hooks(&hooks[..1], &vars, &[]).is_ok());
+    }
+}
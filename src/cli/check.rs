@@ -0,0 +1,103 @@
+use crate::config::Config;
+use crate::templates::parser::TemplateError;
+use crate::templates::renderer;
+use crate::utils::colorspace::Hsl;
+use crate::utils::palette::{self, LabelOptions, Mode, Swatch};
+use crate::utils::paths;
+use anyhow::{Result, bail};
+use std::path::Path;
+
+/// stand in palette so templates can be checked without a wallpaper.
+/// palette_size colors spread around the color wheel
+fn dummy_palette(size: usize) -> Vec<Swatch> {
+    (0..size)
+        .map(|i| Swatch {
+            color: Hsl {
+                hue: i as f64 / size as f64,
+                saturation: 0.6,
+                lightness: if i % 2 == 0 { 0.3 } else { 0.7 },
+            }
+            .to_rgb(),
+            coverage: 1.0 / size as f64,
+        })
+        .collect()
+}
+
+/// file:line:col when the error knows where it happened. the position is
+/// returned too so errors can be listed in file order
+fn describe(path: &Path, e: &anyhow::Error) -> ((usize, usize), String) {
+    match e.downcast_ref::<TemplateError>() {
+        Some(te) => (
+            (te.line, te.col),
+            format!("{}:{}:{}: {}", path.display(), te.line, te.col, te.message),
+        ),
+        None => ((0, 0), format!("{}: {e:#}", path.display())),
+    }
+}
+
+/// render every configured template in strict mode and report what fails.
+/// templates are rendered once in dark and once in light mode so both sides
+/// of an {{#if mode == "light"}} get checked
+pub fn check_templates(config: &Config) -> Result<()> {
+    let templates_dir = paths::templates_dir()?;
+    let partials_dir = paths::partials_dir()?;
+    let out_dir = paths::cache_dir()?;
+
+    let swatches = dummy_palette(config.palette_size.get());
+    let vars = [Mode::Dark, Mode::Light].map(|mode| {
+        let options = LabelOptions {
+            mode,
+            ..config.label_options()
+        };
+        let labels = palette::assign_labels(&swatches, &options);
+        renderer::build_variables(&swatches, &labels, "wallpaper.png")
+    });
+
+    let mut failed = 0;
+    for cfg in &config.templates {
+        let path = templates_dir.join(&cfg.name);
+        if !path.is_file() {
+            eprintln!("{}: template not found", path.display());
+            failed += 1;
+            continue;
+        }
+
+        let mut errors = Vec::new();
+        for vars in &vars {
+            match renderer::check_template(&path, &partials_dir, vars) {
+                Ok(found) => errors.extend(found.iter().map(|e| describe(&path, e))),
+                Err(e) => errors.push(describe(&path, &e)),
+            }
+            if let Some(ref reload) = cfg.reload
+                && let Err(e) =
+                    renderer::substitute_with_partials(reload, vars, &partials_dir, true)
+            {
+                errors.push(((0, 0), format!("{}: reload: {e:#}", path.display())));
+            }
+        }
+        if let Err(e) = renderer::destinations(cfg, &out_dir) {
+            errors.push(((0, 0), format!("{}: output: {e:#}", path.display())));
+        }
+        // most errors dont depend on the mode, and ones inside an {{#each}}
+        // come up once per color. only report each once
+        errors.sort();
+        errors.dedup();
+
+        if errors.is_empty() {
+            println!("{}: ok", path.display());
+        } else {
+            failed += 1;
+            for (_, error) in errors {
+                eprintln!("{error}");
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!(
+            "{failed} of {} templates have errors",
+            config.templates.len()
+        );
+    }
+    Ok(())
+}
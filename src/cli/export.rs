@@ -0,0 +1,35 @@
+use crate::utils::export::{self, ExportFormat};
+use crate::utils::palette::{LabeledColors, Swatch};
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+
+/// write the palette to `output`, or stdout without one
+pub fn export_palette(
+    palette: &[Swatch],
+    labels: &LabeledColors,
+    name: &str,
+    format: ExportFormat,
+    output: Option<&Path>,
+) -> Result<()> {
+    let data = export::export(format, name, palette, labels);
+
+    match output {
+        Some(path) => {
+            fs::write(path, data)
+                .with_context(|| format!("Failed to write palette to {}", path.display()))?;
+            println!("Exported palette to {}", path.display());
+        }
+        None => {
+            let mut stdout = std::io::stdout();
+            if format == ExportFormat::Ase && stdout.is_terminal() {
+                bail!("ase is a binary format, use -o to write it to a file");
+            }
+            stdout
+                .write_all(&data)
+                .context("Failed to write palette to stdout")?;
+        }
+    }
+    Ok(())
+}
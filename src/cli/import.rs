@@ -0,0 +1,54 @@
+use crate::config::Config;
+use crate::utils::{import, paths};
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+
+/// copy a color scheme into the palettes dir and add a [theme.name] entry for it,
+/// so it can be applied like any other named theme
+pub fn import_palette(config: &Config, file: &Path, name: Option<String>) -> Result<()> {
+    // parse it first so a broken scheme never makes it into the config
+    let colors = import::load(file)?;
+
+    let name = match name {
+        Some(name) => name,
+        None => file
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .context("Can't name the theme after the file, pass --name")?
+            .to_string(),
+    };
+    if name.is_empty() || name.contains('/') {
+        bail!("Invalid theme name '{name}'");
+    }
+    if config.theme.contains_key(&name) {
+        bail!(
+            "A theme named '{name}' already exists in config\n  Use --name to pick another name."
+        );
+    }
+
+    let dir = paths::palettes_dir()?;
+    fs::create_dir_all(&dir).context("Failed to create palettes directory")?;
+    let dest = match file.extension().and_then(|e| e.to_str()) {
+        Some(ext) => dir.join(format!("{name}.{ext}")),
+        None => dir.join(&name),
+    };
+    if dest.exists() {
+        bail!("A palette already exists at {}", dest.display());
+    }
+    fs::copy(file, &dest)
+        .with_context(|| format!("Failed to copy palette to {}", dest.display()))?;
+
+    // without its config entry the copy would only block importing it again
+    if let Err(e) = Config::append_theme_entry(&name, &dest.to_string_lossy()) {
+        let _ = fs::remove_file(&dest);
+        return Err(e);
+    }
+    println!(
+        "Imported {} colors as theme '{name}' to {}",
+        colors.len(),
+        dest.display()
+    );
+    println!("Apply it with `chlorophyll from {name}`");
+    Ok(())
+}
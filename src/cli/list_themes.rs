@@ -1,9 +1,8 @@
 use crate::config::ThemeConfig;
 use crate::theme::{Theme, list_wallpapers};
-use crate::utils::PaletteSize;
+use crate::utils::PaletteOptions;
 use crate::utils::cache;
-use crate::utils::colorspace::Rgb;
-use crate::utils::palette::{self, LabeledColors};
+use crate::utils::palette::{self, LabelOptions, LabeledColors, Swatch};
 use std::path::Path;
 use anyhow::{Ok, Result};
 use std::collections::HashMap;
@@ -19,7 +18,7 @@ use ratatui::{
 // a wallpaper and its cached palette if one exists
 struct WallpaperEntry {
     name: String,
-    palette: Option<(Vec<Rgb>, LabeledColors)>,
+    palette: Option<(Vec<Swatch>, LabeledColors)>,
     is_custom_theme: bool,
 }
 
@@ -104,11 +103,12 @@ impl ListApp {
 
 fn load_cached_palette(
     path: &Path,
-    palette_size: PaletteSize,
-) -> Option<(Vec<Rgb>, LabeledColors)> {
-    let hash = Theme::new(path.to_path_buf()).hash(palette_size).ok()?;
+    options: &PaletteOptions,
+    label_options: &LabelOptions,
+) -> Option<(Vec<Swatch>, LabeledColors)> {
+    let hash = Theme::new(path.to_path_buf()).hash(options).ok()?;
     let colors = cache::load_cache(&hash).ok().flatten()?;
-    let labels = palette::assign_labels(&colors);
+    let labels = palette::assign_labels(&colors, label_options);
     Some((colors, labels))
 }
 
@@ -116,7 +116,8 @@ fn load_cached_palette(
 /// previews wallpapers in wallpaper dir and cached previews if available
 pub fn list_themes(
     wallpaper_dir: &str,
-    palette_size: PaletteSize,
+    options: &PaletteOptions,
+    label_options: &LabelOptions,
     custom_themes: &HashMap<String, ThemeConfig>,
 ) -> Result<Option<String>> {
     let paths = list_wallpapers(wallpaper_dir)?;
@@ -136,7 +137,7 @@ pub fn list_themes(
                 .unwrap_or("?")
                 .to_string();
 
-            let palette = load_cached_palette(&path, palette_size);
+            let palette = load_cached_palette(&path, options, label_options);
 
             WallpaperEntry {
                 name,
@@ -151,7 +152,20 @@ pub fn list_themes(
     theme_names.sort();
     wallpapers.extend(theme_names.into_iter().map(|name| {
         let tc = &custom_themes[name];
-        let palette = load_cached_palette(Path::new(&tc.path), palette_size);
+        let palette = if tc.palette.is_some() {
+            // imported palettes are cheap to load, so they dont need a cache
+            let colors = Theme::from_config(name, tc)
+                .and_then(|theme| theme.palette(options))
+                .ok();
+            colors.map(|colors| {
+                let labels = palette::assign_labels(&colors, label_options);
+                (colors, labels)
+            })
+        } else {
+            tc.path
+                .as_ref()
+                .and_then(|path| load_cached_palette(Path::new(path), options, label_options))
+        };
         WallpaperEntry {
             name: name.clone(),
             palette,
@@ -286,7 +300,7 @@ fn draw_preview(frame: &mut Frame, app: &ListApp, area: Rect) {
 
 // renders the color grid, same logic as preview.rs but adapted
 // for the smaller area inside the list view
-fn draw_swatches(frame: &mut Frame, palette: &[Rgb], labels: &LabeledColors, area: Rect) {
+fn draw_swatches(frame: &mut Frame, palette: &[Swatch], labels: &LabeledColors, area: Rect) {
     let cols_per_row = 8usize;
     let num_rows = palette.len().div_ceil(cols_per_row);
 
@@ -302,17 +316,27 @@ fn draw_swatches(frame: &mut Frame, palette: &[Rgb], labels: &LabeledColors, are
             vec![Constraint::Ratio(1, cols_per_row as u32); row_colors.len()];
         let col_areas = Layout::horizontal(&col_constraints).split(row_areas[row_idx]);
 
-        for (col_idx, c) in row_colors.iter().enumerate() {
+        for (col_idx, s) in row_colors.iter().enumerate() {
+            let c = &s.color;
             let bg = Color::Rgb(c.0, c.1, c.2);
+            let t = c.text_color();
+            let text = Color::Rgb(t.0, t.1, t.2);
             let hex = c.hex();
-            let label = labels.label_for(c);
+            let label = labels.label_for(row_idx * cols_per_row + col_idx);
 
-            // vertically center the hex + optional label within the swatch
-            let content_lines: u16 = if label.is_some() { 2 } else { 1 };
+            // vertically center the hex + coverage + optional label within the swatch
+            let content_lines: u16 = if label.is_some() { 3 } else { 2 };
             let padding = (row_height.saturating_sub(content_lines)) / 2;
             let mut lines: Vec<Line> = vec![Line::from(""); padding as usize];
             lines.push(
-                Line::from(Span::styled(hex, Style::default().fg(Color::Black).bg(bg))).centered(),
+                Line::from(Span::styled(hex, Style::default().fg(text).bg(bg))).centered(),
+            );
+            lines.push(
+                Line::from(Span::styled(
+                    format!("{:.1}%", s.coverage * 100.0),
+                    Style::default().fg(text).bg(bg),
+                ))
+                .centered(),
             );
             if let Some(name) = label {
                 lines.push(
@@ -1,16 +1,29 @@
 mod change_theme;
+mod check;
+mod export;
+mod import;
 mod list_themes;
 mod preview;
+mod show;
+mod vars;
 
 pub(crate) use change_theme::change_theme;
+use check::check_templates;
+use export::export_palette;
+use import::import_palette;
 use list_themes::list_themes;
 use preview::preview_palette;
+use show::show_palette;
+use vars::{VarsFormat, print_vars};
 
 use crate::config::{Config, ThemeConfig};
 use crate::templates::comptime_templates::{find_comptime_template, list_names};
+use crate::templates::renderer;
 use crate::theme::{Theme, find_wallpaper};
 use crate::utils::cache::clear_cache;
-use crate::utils::palette;
+use crate::utils::export::ExportFormat;
+use crate::utils::Extraction;
+use crate::utils::palette::{self, Mode};
 use crate::utils::history::reapply_last_wallpaper;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
@@ -24,6 +37,12 @@ pub struct Cli {
     /// Force re-extraction of color palette (updates cache)
     #[arg(long, short = 'f')]
     force: bool,
+    /// Override the `extraction` strategy from config for this run
+    #[arg(long, value_enum)]
+    extraction: Option<Extraction>,
+    /// Override the `mode` from config for this run
+    #[arg(long, value_enum)]
+    mode: Option<Mode>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -44,11 +63,56 @@ enum Command {
     ///
     /// Usage: chlorophyll preview <name>
     Preview { name: String },
+    /// Print the palette of a wallpaper or named theme without opening the
+    /// preview, for scripts and logs. Falls back to 256 colors when COLORTERM
+    /// isn't truecolor
+    ///
+    /// Usage: chlorophyll show <name> [--json]
+    Show {
+        name: String,
+        /// Print the palette as json instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print every template variable for a wallpaper or named theme, for shell
+    /// scripts that dont go through templates
+    ///
+    /// Usage: chlorophyll vars <name> [--format env|json|shell]
+    Vars {
+        name: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: VarsFormat,
+    },
     /// Generate the cache for a wallpaper in your wallpapers directory
     /// without applying it
     ///
     /// Usage: chlorophyll cache <name>
     Cache { name: String },
+    /// Check every configured template for errors without applying a theme.
+    /// Templates are rendered strictly against a stand-in palette
+    Check,
+    /// Export the palette of a wallpaper or named theme, for loading it into
+    /// GIMP, Inkscape, Krita and the like
+    ///
+    /// Usage: chlorophyll export <name> --format <format> [-o <file>]
+    Export {
+        name: String,
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// File to write to, defaults to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Import a color scheme as a named theme. Takes base16/base24 YAML, pywal
+    /// colors.json, Xresources or a list of hex colors
+    ///
+    /// Usage: chlorophyll import <file> [--name <name>]
+    Import {
+        file: PathBuf,
+        /// Name of the theme, defaults to the file name
+        #[arg(long)]
+        name: Option<String>,
+    },
     /// Write a template to ~/.config/chlorophyll/templates/<name>
     Template {
         name: String,
@@ -78,15 +142,24 @@ impl Cli {
             },
             // Commands that do require the config to be loaded
             command => {
-                let config = Config::load()?;
+                let mut config = Config::load()?;
+                if let Some(extraction) = self.extraction {
+                    config.extraction = extraction;
+                }
+                if let Some(mode) = self.mode {
+                    config.mode = mode;
+                }
                 match command {
                     Command::Reapply => {
                         reapply_last_wallpaper(&config, self.force)?;
                     }
                     Command::List => {
-                        if let Some(name) =
-                            list_themes(&config.wallpaper_dir, config.palette_size, &config.theme)?
-                        {
+                        if let Some(name) = list_themes(
+                            &config.wallpaper_dir,
+                            &config.palette_options(),
+                            &config.label_options(),
+                            &config.theme,
+                        )? {
                             let (theme, tc) = resolve_theme(&name, &config, self.force)?;
                             let named = tc.map(|tc| (name.as_str(), tc));
                             change_theme(&theme, &config, named)?;
@@ -99,14 +172,47 @@ impl Cli {
                     }
                     Command::Preview { name } => {
                         let (theme, _tc) = resolve_theme(&name, &config, self.force)?;
-                        let colors = theme.palette(config.palette_size)?;
-                        let labels = palette::assign_labels(&colors);
+                        let colors = theme.palette(&config.palette_options())?;
+                        let labels = palette::assign_labels(&colors, &config.label_options());
                         preview_palette(&colors, &name, &labels)?;
                     }
+                    Command::Show { name, json } => {
+                        let (theme, _tc) = resolve_theme(&name, &config, self.force)?;
+                        let colors = theme.palette(&config.palette_options())?;
+                        let labels = palette::assign_labels(&colors, &config.label_options());
+                        show_palette(&colors, &labels, &name, json)?;
+                    }
+                    Command::Vars { name, format } => {
+                        let (theme, _tc) = resolve_theme(&name, &config, self.force)?;
+                        let colors = theme.palette(&config.palette_options())?;
+                        let labels = palette::assign_labels(&colors, &config.label_options());
+                        let wallpaper = theme
+                            .wallpaper
+                            .map(|w| w.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let vars = renderer::build_variables(&colors, &labels, &wallpaper);
+                        print_vars(&vars, format)?;
+                    }
                     Command::Cache { name } => {
                         let (theme, _tc) = resolve_theme(&name, &config, self.force)?;
                         // generating the palette will cache the results
-                        theme.palette(config.palette_size)?;
+                        theme.palette(&config.palette_options())?;
+                    }
+                    Command::Check => {
+                        check_templates(&config)?;
+                    }
+                    Command::Export {
+                        name,
+                        format,
+                        output,
+                    } => {
+                        let (theme, _tc) = resolve_theme(&name, &config, self.force)?;
+                        let colors = theme.palette(&config.palette_options())?;
+                        let labels = palette::assign_labels(&colors, &config.label_options());
+                        export_palette(&colors, &labels, &name, format, output.as_deref())?;
+                    }
+                    Command::Import { file, name } => {
+                        import_palette(&config, &file, name)?;
                     }
                     Command::Init | Command::Clear | Command::Template { .. } => unreachable!(),
                 }
@@ -125,7 +231,7 @@ fn resolve_theme<'a>(
     force: bool,
 ) -> Result<(Theme, Option<&'a ThemeConfig>)> {
     let (theme, tc) = if let Some(tc) = config.theme.get(name) {
-        (Theme::new(PathBuf::from(&tc.path)), Some(tc))
+        (Theme::from_config(name, tc)?, Some(tc))
     } else {
         let theme = find_wallpaper(&config.wallpaper_dir, name).map(Theme::new)?;
         (theme, None)
@@ -1,5 +1,4 @@
-use crate::utils::colorspace::Rgb;
-use crate::utils::palette::LabeledColors;
+use crate::utils::palette::{LabeledColors, Swatch};
 use anyhow::{Ok, Result};
 use ratatui::{
     Frame,
@@ -12,13 +11,13 @@ use ratatui::{
 
 // holds the palette data needed to render the preview tui
 struct PreviewApp<'a> {
-    palette: &'a [Rgb],
+    palette: &'a [Swatch],
     name: &'a str,
     labels: &'a LabeledColors,
 }
 
 impl<'a> PreviewApp<'a> {
-    fn new(palette: &'a [Rgb], name: &'a str, labels: &'a LabeledColors) -> Self {
+    fn new(palette: &'a [Swatch], name: &'a str, labels: &'a LabeledColors) -> Self {
         Self {
             palette,
             name,
@@ -30,10 +29,11 @@ impl<'a> PreviewApp<'a> {
     /// with these layouts, which ship areas to bind components to.
     /// https://ratatui.rs/concepts/layout/
     fn draw(&self, frame: &mut Frame) {
-        let [title_area, main_area, footer_area] = Layout::vertical([
+        let [title_area, main_area, contrast_area, footer_area] = Layout::vertical([
             Constraint::Length(2),
             Constraint::Fill(1),
             Constraint::Length(1),
+            Constraint::Length(1),
         ])
         .areas(frame.area());
 
@@ -50,15 +50,37 @@ impl<'a> PreviewApp<'a> {
         // split into rows of 8 with square-ish cells
         self.draw_swatches(frame, main_area);
 
+        // contrast ratios of the text labels against the background, each drawn
+        // in its own color on the background so you can judge it by eye too
+        self.draw_contrast(frame, contrast_area);
+
         // footer with information on how to leave the view (vim could never)
         let footer = Paragraph::new(Line::from("q/Esc to quit").centered())
             .style(Style::default().fg(Color::DarkGray));
         frame.render_widget(footer, footer_area);
     }
 
+    fn draw_contrast(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let bg = self.labels.background;
+        let mut spans = vec![Span::styled(
+            "contrast vs bg: ",
+            Style::default().fg(Color::DarkGray),
+        )];
+        for (name, c, ratio) in self.labels.contrast_ratios() {
+            spans.push(Span::styled(
+                format!(" {name} {ratio:.1}:1 "),
+                Style::default()
+                    .fg(Color::Rgb(c.0, c.1, c.2))
+                    .bg(Color::Rgb(bg.0, bg.1, bg.2)),
+            ));
+            spans.push(Span::raw(" "));
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans).centered()), area);
+    }
+
     fn draw_swatches(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
         let cols_per_row = 8usize;
-        let rows: Vec<&[Rgb]> = self.palette.chunks(cols_per_row).collect();
+        let rows: Vec<&[Swatch]> = self.palette.chunks(cols_per_row).collect();
         let num_rows = rows.len();
 
         // terminal chars are ~2:1 height:width, so row height = col_width / 2 for squares
@@ -73,19 +95,31 @@ impl<'a> PreviewApp<'a> {
                 vec![Constraint::Ratio(1, cols_per_row as u32); row_colors.len()];
             let col_areas = Layout::horizontal(&col_constraints).split(row_areas[row_idx]);
 
-            for (col_idx, c) in row_colors.iter().enumerate() {
+            for (col_idx, s) in row_colors.iter().enumerate() {
+                let c = &s.color;
                 let bg = Color::Rgb(c.0, c.1, c.2);
+                // fixed text colors disappear on some swatches, so go by contrast
+                let t = c.text_color();
+                let text = Color::Rgb(t.0, t.1, t.2);
                 let hex = c.hex();
-                let label = self.labels.label_for(c);
+                let label = self.labels.label_for(row_idx * cols_per_row + col_idx);
 
                 // vertically center the hex label within the swatch
-                let content_lines: u16 = if label.is_some() { 2 } else { 1 };
+                let content_lines: u16 = if label.is_some() { 3 } else { 2 };
                 let padding = (row_height.saturating_sub(content_lines)) / 2;
                 let mut lines: Vec<Line> = vec![Line::from(""); padding as usize];
                 lines.push(
-                    Line::from(Span::styled(hex, Style::default().fg(Color::Black).bg(bg)))
+                    Line::from(Span::styled(hex, Style::default().fg(text).bg(bg)))
                         .centered(),
                 );
+                // how much of the wallpaper this color covers
+                lines.push(
+                    Line::from(Span::styled(
+                        format!("{:.1}%", s.coverage * 100.0),
+                        Style::default().fg(text).bg(bg),
+                    ))
+                    .centered(),
+                );
                 if let Some(name) = label {
                     lines.push(
                         Line::from(Span::styled(
@@ -107,7 +141,7 @@ impl<'a> PreviewApp<'a> {
 }
 
 /// display the color palette to the user of the wallpaper that they provide
-pub fn preview_palette(palette: &[Rgb], name: &str, labels: &LabeledColors) -> Result<()> {
+pub fn preview_palette(palette: &[Swatch], name: &str, labels: &LabeledColors) -> Result<()> {
     let app = PreviewApp::new(palette, name, labels);
 
     // tuis are event-based unlike video games where you render frames as fast as possible,
@@ -0,0 +1,84 @@
+use crate::utils::colorspace::Rgb;
+use crate::utils::export::{self, ExportFormat};
+use crate::utils::palette::{LabeledColors, Swatch};
+use anyhow::{Context, Result};
+use std::io::Write;
+
+/// escape code that sets the background to `c`, approximated on terminals
+/// that dont advertise truecolor in COLORTERM
+fn bg_escape(c: &Rgb, truecolor: bool) -> String {
+    if truecolor {
+        format!("\x1b[48;2;{};{};{}m", c.0, c.1, c.2)
+    } else {
+        format!("\x1b[48;5;{}m", c.ansi256())
+    }
+}
+
+fn fg_escape(c: &Rgb, truecolor: bool) -> String {
+    if truecolor {
+        format!("\x1b[38;2;{};{};{}m", c.0, c.1, c.2)
+    } else {
+        format!("\x1b[38;5;{}m", c.ansi256())
+    }
+}
+
+/// a block of color followed by its hex value
+fn swatch(c: &Rgb, truecolor: bool) -> String {
+    format!("{}      \x1b[0m {}", bg_escape(c, truecolor), c.hex())
+}
+
+/// print the palette inline, without taking over the terminal like preview does.
+/// with json set the palette is printed like `export --format json` instead
+pub fn show_palette(
+    palette: &[Swatch],
+    labels: &LabeledColors,
+    name: &str,
+    json: bool,
+) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    if json {
+        out.write_all(&export::export(ExportFormat::Json, name, palette, labels))
+            .context("Failed to write to stdout")?;
+        return Ok(());
+    }
+
+    let truecolor =
+        std::env::var("COLORTERM").is_ok_and(|term| term == "truecolor" || term == "24bit");
+
+    let mut text = format!("{name} ({})\n\n", labels.mode.as_str());
+    for (i, s) in palette.iter().enumerate() {
+        let label = labels
+            .label_for(i)
+            .map(|l| format!("  [{l}]"))
+            .unwrap_or_default();
+        text.push_str(&format!(
+            "{}  color{i:<3} {:>5.1}%{label}\n",
+            swatch(&s.color, truecolor),
+            s.coverage * 100.0
+        ));
+    }
+
+    text.push('\n');
+    for (label, c) in [
+        ("background", labels.background),
+        ("foreground", labels.foreground),
+        ("primary", labels.primary),
+        ("secondary", labels.secondary),
+    ] {
+        text.push_str(&format!("{}  {label}\n", swatch(&c, truecolor)));
+    }
+
+    // each ratio drawn in its own color on the background, like preview
+    text.push_str("\ncontrast vs bg:");
+    let bg = bg_escape(&labels.background, truecolor);
+    for (label, c, ratio) in labels.contrast_ratios() {
+        text.push_str(&format!(
+            " {bg}{} {label} {ratio:.1}:1 \x1b[0m",
+            fg_escape(&c, truecolor)
+        ));
+    }
+    text.push('\n');
+
+    out.write_all(text.as_bytes())
+        .context("Failed to write to stdout")
+}
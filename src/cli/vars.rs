@@ -0,0 +1,51 @@
+use crate::templates::renderer;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// how `chlorophyll vars` prints the variables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum VarsFormat {
+    /// unquoted CHLOROPHYLL_KEY=value lines, for env files that are read line by
+    /// line like docker's --env-file. use shell for anything sh parses
+    #[default]
+    Env,
+    /// a json object with the template names as keys
+    Json,
+    /// export CHLOROPHYLL_KEY='value' lines to `source` or `eval`
+    Shell,
+}
+
+/// single quote a value for sh, unless it is plain enough not to need it
+fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "#.,/_-+:%".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// print every template variable in the given format
+pub fn print_vars(vars: &HashMap<String, String>, format: VarsFormat) -> Result<()> {
+    let text = match format {
+        VarsFormat::Json => {
+            let sorted: BTreeMap<_, _> = vars.iter().collect();
+            serde_json::to_string_pretty(&sorted)? + "\n"
+        }
+        VarsFormat::Env => renderer::env_vars(vars)
+            .into_iter()
+            .map(|(name, value)| format!("{name}={value}\n"))
+            .collect(),
+        VarsFormat::Shell => renderer::env_vars(vars)
+            .into_iter()
+            .map(|(name, value)| format!("export {name}={}\n", shell_quote(&value)))
+            .collect(),
+    };
+    std::io::stdout()
+        .write_all(text.as_bytes())
+        .context("Failed to write to stdout")
+}
@@ -1,25 +1,130 @@
 use crate::utils::paths;
-use crate::utils::PaletteSize;
+use crate::utils::palette::{LabelOptions, Mode};
+use crate::utils::{Extraction, PaletteOptions, PaletteSize};
 use anyhow::{Context, Result, bail};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
+use std::time::Duration;
 
 #[derive(Deserialize)]
 pub struct Template {
     pub name: String,
+    /// run after the template is written, with the same variables substituted
     pub reload: Option<String>,
+    /// where the rendered file is written, one path or a list of them. ~ and
+    /// $VARS are expanded. empty means the cache dir
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub output: Vec<String>,
+}
+
+/// lets `output = "a"` and `output = ["a", "b"]` both work
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(path) => vec![path],
+        OneOrMany::Many(paths) => paths,
+    })
 }
 
 #[derive(Deserialize)]
 pub struct Hook {
     pub command: String,
+    /// names the hook's log file, hooks/<name>.log instead of hooks/hook-N.log
+    pub name: Option<String>,
+    /// wait for the hook to exit before the next one starts
+    #[serde(default)]
+    pub wait: bool,
+    /// kill the hook if it runs longer than this, e.g. "5s" or "500ms"
+    #[serde(default, deserialize_with = "duration")]
+    pub timeout: Option<Duration>,
+    #[serde(default)]
+    pub on_failure: OnFailure,
+    /// write the hook's stdout to a log in the cache dir
+    #[serde(default)]
+    pub stdout: bool,
+    /// write the hook's stderr to the same log
+    #[serde(default)]
+    pub stderr: bool,
+}
+
+impl Hook {
+    /// whether chlorophyll waits for the hook. a timeout or abort needs the
+    /// hook's exit, so they wait too
+    pub fn blocking(&self) -> bool {
+        self.wait || self.timeout.is_some() || self.on_failure == OnFailure::Abort
+    }
+
+    /// the log file name in cache/hooks, by name so it survives reordering
+    /// [[hooks]], else by position
+    pub fn log_name(&self, index: usize) -> Result<String> {
+        match self.name {
+            Some(ref name) => {
+                if name.is_empty() || name.contains('/') {
+                    bail!("Invalid hook name '{name}'");
+                }
+                Ok(format!("{name}.log"))
+            }
+            None => Ok(format!("hook-{index}.log")),
+        }
+    }
+}
+
+/// what happens when a hook exits non-zero, times out or cant be started
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnFailure {
+    /// print a warning and carry on with the next hook
+    #[default]
+    Warn,
+    /// stop applying the theme and exit non-zero
+    Abort,
+}
+
+/// "500ms", "5s", "2m" or a plain number of seconds
+fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid duration '{s}'"))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        unit => bail!("Invalid duration '{s}': unknown unit '{unit}', use ms, s or m"),
+    };
+    Duration::try_from_secs_f64(seconds).with_context(|| format!("Invalid duration '{s}'"))
+}
+
+fn duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_duration(&s)
+        .map(Some)
+        .map_err(|e| serde::de::Error::custom(format!("{e:#}")))
 }
 
 #[derive(Deserialize)]
 pub struct ThemeConfig {
-    pub path: String,
+    /// the wallpaper. optional when the colors come from `palette`
+    pub path: Option<String>,
+    /// a base16/base24 yaml, pywal colors.json, Xresources or hex list file to
+    /// take the colors from instead of extracting them from the wallpaper
+    pub palette: Option<String>,
     pub wallpaper_command: Option<String>,
     pub wallpaper_kill: Option<String>,
 }
@@ -32,8 +137,36 @@ pub struct Config {
     /// number of colors to extract from the wallpaper
     #[serde(default = "default_palette_size")]
     pub palette_size: PaletteSize,
+    /// quantizer used to extract the palette, "median_cut", "kmeans" or "wu"
+    #[serde(default)]
+    pub extraction: Extraction,
+    /// the wallpaper is shrunk to fit in a square of this size before extraction.
+    /// 0 uses the full resolution image
+    #[serde(default = "default_thumbnail_size")]
+    pub thumbnail_size: u32,
+    /// how much palette ordering favors colors covering a lot of the wallpaper
+    /// over vibrant ones. 0 is pure vibrancy, 1 is pure coverage
+    #[serde(default)]
+    pub coverage_weight: f64,
+    /// colors closer than this perceptual (OKLab) distance are merged, and the
+    /// freed slots refilled with the next best candidates. 0 disables merging
+    #[serde(default = "default_merge_distance")]
+    pub merge_distance: f64,
+    /// "dark", "light" or "auto" to pick one from the wallpaper's brightness
+    #[serde(default)]
+    pub mode: Mode,
+    /// WCAG contrast ratio foreground, primary and secondary are pushed to reach
+    /// against background, i.e. 4.5 for readable text
+    pub min_contrast: Option<f64>,
     #[serde(default)]
     pub templates: Vec<Template>,
+    /// unknown variables and unterminated {{ fail the render, and nothing is
+    /// written unless every template renders
+    #[serde(default)]
+    pub strict_templates: bool,
+    /// recolor open terminals with OSC escape sequences when the theme changes
+    #[serde(default)]
+    pub terminal_sequences: bool,
     /// post-theme-change hooks. can use {{color0}}, {{wallpaper}}, etc.
     /// i.e. setting border colors on your window manager, or wallpaper for your
     /// lock screen
@@ -48,12 +181,39 @@ fn default_palette_size() -> PaletteSize {
     PaletteSize::new(16)
 }
 
+fn default_thumbnail_size() -> u32 {
+    128
+}
+
+fn default_merge_distance() -> f64 {
+    0.04
+}
+
 impl Config {
+    /// the settings that shape palette extraction, and therefore the cache key
+    pub fn palette_options(&self) -> PaletteOptions {
+        PaletteOptions {
+            size: self.palette_size,
+            extraction: self.extraction,
+            thumbnail_size: self.thumbnail_size,
+            coverage_weight: self.coverage_weight.clamp(0.0, 1.0),
+            merge_distance: self.merge_distance.max(0.0),
+        }
+    }
+
     /// returns the path to the config file
     pub fn config_path() -> Result<std::path::PathBuf> {
         paths::config_file()
     }
 
+    /// the settings that shape how labels are picked from the palette
+    pub fn label_options(&self) -> LabelOptions {
+        LabelOptions {
+            mode: self.mode,
+            min_contrast: self.min_contrast,
+        }
+    }
+
     /// loads the config and returns it
     pub fn load() -> Result<Self> {
         let config_path = Self::config_path()?;
@@ -97,25 +257,106 @@ wallpaper_dir = "{home}/.config/wallpapers"
 # wallpaper_kill = "pkill swaybg"
 
 # Number of colors to extract from the wallpaper
-# Remember that if you change this you'll have to change the colorN's that
-# your templates reference
+# Templates that list colors with {{{{#each colors}}}} adapt to it, ones that
+# reference colorN directly have to be updated when you change it
 # palette_size = 16
 
+# Algorithm used to extract the palette: "median_cut" (default), "kmeans" or "wu"
+# kmeans is slower but keeps small accent colors from being averaged away.
+# wu works on a color histogram, so it stays fast on large thumbnails.
+# Can be overridden per run with --extraction
+# extraction = "median_cut"
+
+# Size the wallpaper is shrunk to before extraction. Bigger is more accurate
+# but slower with median_cut and kmeans. 0 uses the full resolution image
+# thumbnail_size = 128
+
+# Colors are ordered by vibrancy. Raise this towards 1.0 to rank colors that
+# cover more of the wallpaper higher instead
+# coverage_weight = 0.0
+
+# Colors that look nearly the same (closer than this OKLab distance) are merged
+# and the freed slots refilled with the next best colors. ~0.02 is barely
+# noticeable, 0.1 is a clearly different color. 0 disables merging
+# merge_distance = 0.04
+
+# Theme mode: "dark" (default), "light", or "auto" to pick one from how bright
+# the wallpaper is. Templates can check it with {{{{mode}}}}. The extracted palette
+# is shared by both modes, so switching never re-extracts.
+# Can be overridden per run with --mode
+# mode = "dark"
+
+# Minimum WCAG contrast ratio between {{{{background}}}} and the foreground,
+# primary and secondary labels. Labels below it get lightened (or darkened on
+# light backgrounds) until they reach it. 4.5 is the usual target for text
+# min_contrast = 4.5
+
+# Recolor terminals that are already open by sending them escape sequences, the
+# way pywal does. The sequences are also saved to ~/.cache/chlorophyll/sequences,
+# `cat` it from your shell rc so new shells pick up the theme too
+# terminal_sequences = true
+
 # Optional: commands to run after the theme is applied.
 # Uses the same variables as templates: {{{{color0}}}}, {{{{color0.strip}}}}, etc.
 # Named colors: {{{{background}}}}, {{{{foreground}}}}, {{{{primary}}}}, {{{{secondary}}}}
-# Each supports .strip, .rgb, .red, .green, .blue suffixes
+# Terminal colors picked by hue: {{{{ansi.red}}}}, {{{{ansi.bright_red}}}}, ... for
+# black, red, green, yellow, blue, magenta, cyan and white
+# Editor colors: {{{{syntax.keyword}}}}, function, string, number, type, constant,
+# operator (picked by hue), variable, punctuation, comment, and the ui colors
+# surface, overlay, border, selection and cursorline. Text roles are kept
+# readable against the background
+# Each supports .strip, .rgb, .0x, .red, .green, .blue suffixes
+# {{{{colorN.coverage}}}} is the share of the wallpaper a color covers, in [0-1]
+#
+# Colors can be piped through filters, e.g. {{{{background | darken 0.1}}}} or
+# {{{{primary | alpha 0.8 | rgba}}}}. Color filters: lighten, darken, saturate,
+# desaturate (amounts in 0-1), rotate (degrees), complement, alpha (0-1) and
+# mix <color> [weight]. Format filters: hex, strip, 0x, rgb, rgba, red, green,
+# blue, hsv and oklch
+#
+# {{{{#each colors}}}}...{{{{/each}}}} repeats its contents for every palette color
+# ({{{{#each ansi}}}} for the 16 terminal colors), with {{{{this}}}}, {{{{this.strip}}}},
+# {{{{@index}}}}, {{{{@first}}}} and {{{{@last}}}} inside. {{{{#if mode == "light"}}}}...
+# {{{{else}}}}...{{{{/if}}}} picks a branch, != and a bare variable work too
+#
+# Hooks also get every variable in their environment, upper cased with . as _,
+# like $CHLOROPHYLL_COLOR0, $CHLOROPHYLL_PRIMARY_STRIP and $CHLOROPHYLL_MODE,
+# plus $CHLOROPHYLL_THEME and $CHLOROPHYLL_PALETTE, the path to the palette as
+# json. `chlorophyll vars <name>` lists them all
+#
+# Hooks start in order and normally run in the background. wait = true holds
+# the next hook until this one exits, timeout = "5s" kills it after that long,
+# and on_failure = "abort" stops applying the theme (and exits non-zero) when it
+# fails instead of warning. stdout = true and stderr = true keep its output in
+# ~/.cache/chlorophyll/hooks/<name>.log, or hook-N.log for hooks without a
+# name, N counting [[hooks]] from 0
 #
 # [[hooks]]
+# name = "river"
 # command = "riverctl border-color-focused {{{{color0.strip}}}}"
+# wait = true
+# timeout = "5s"
+# on_failure = "abort"
+# stderr = true
 
 # Optional: reload hooks for templates
 # Place template files in ~/.config/chlorophyll/templates/
-# Rendered output goes to ~/.cache/chlorophyll/
+# Blocks shared between templates can go in templates/partials/ and be pulled
+# in with {{{{> name}}}} (a file called name, or name with any extension)
+# Rendered output goes to ~/.cache/chlorophyll/, unless the template sets an
+# output. ~ and $VARS are expanded, and a list writes the same file to each path
+# The reload command runs after the file is written and can use the template
+# variables too, e.g. "gsettings set ... color-scheme prefer-{{{{mode}}}}"
 #
 # [[templates]]
 # name = "colors-waybar.css"
 # reload = "killall -SIGUSR2 waybar"
+# output = "~/.config/waybar/colors.css"
+#
+# Unknown variables are normally left in the output with a warning. With strict
+# templates they are errors, and no template is written unless all of them
+# render. `chlorophyll check` reports the same errors without applying a theme
+# strict_templates = true
 #
 # [[templates]]
 # name = "colors-rofi.rasi"
@@ -127,6 +368,12 @@ wallpaper_dir = "{home}/.config/wallpapers"
 # path = "{home}/.config/wallpapers/animated_bg.gif"
 # wallpaper_command = "swww img {{{{wallpaper}}}}"
 # wallpaper_kill = "pkill swww"
+#
+# Themes can also use a hand made color scheme instead of extracting one, either
+# next to a wallpaper or on their own. `chlorophyll import <file>` adds these
+#
+# [theme.gruvbox]
+# palette = "{home}/.config/chlorophyll/palettes/gruvbox.yaml"
 "#
         );
 
@@ -149,7 +396,23 @@ wallpaper_dir = "{home}/.config/wallpapers"
 
     /// Append a [[templates]] entry to config.toml as raw text.
     /// This avoids a deserialize/serialize round-trip that would strip comments.
-    pub fn append_template_entry(name: &str, reload: Option<&str>) -> Result<()> {
+    pub fn append_template_entry(name: &str, reload: Option<&str>, output: &[&str]) -> Result<()> {
+        Self::append_entry(&template_entry(name, reload, output)?)
+    }
+
+    /// Append a [[hooks]] entry to config.toml, same as append_template_entry
+    pub fn append_hook_entry(command: &str) -> Result<()> {
+        let mut hook = toml::Table::new();
+        hook.insert("command".to_string(), command.into());
+        Self::append_entry(&entry("hooks", vec![toml::Value::from(hook)].into())?)
+    }
+
+    /// Append a [theme.name] entry that takes its colors from a palette file
+    pub fn append_theme_entry(name: &str, palette: &str) -> Result<()> {
+        Self::append_entry(&theme_entry(name, palette)?)
+    }
+
+    fn append_entry(entry: &str) -> Result<()> {
         let config_path = Self::config_path()?;
         // if no config path return early
         if !config_path.exists() {
@@ -162,16 +425,106 @@ wallpaper_dir = "{home}/.config/wallpapers"
             .open(&config_path)
             .context("Failed to open config file for appending")?;
 
-        let mut entry = format!("\n[[templates]]\nname = \"{name}\"\n");
-        // if there is a reload command included, add that too
-        if let Some(cmd) = reload {
-            entry.push_str(&format!("reload = \"{cmd}\"\n"));
-        }
-
         // append the entry to the file
         file.write_all(entry.as_bytes())
-            .context("Failed to append template entry to config")?;
+            .context("Failed to append entry to config")?;
 
         Ok(())
     }
 }
+
+/// a top level `key` holding `value` as config text. going through toml keeps
+/// quotes and backslashes in names, paths and commands escaped
+fn entry(key: &str, value: toml::Value) -> Result<String> {
+    let mut entry = toml::Table::new();
+    entry.insert(key.to_string(), value);
+    let entry = toml::to_string(&entry).with_context(|| format!("Failed to write {key} entry"))?;
+    Ok(format!("\n{entry}"))
+}
+
+/// a [[templates]] entry. one output is written as a string, more as a list
+fn template_entry(name: &str, reload: Option<&str>, output: &[&str]) -> Result<String> {
+    let mut template = toml::Table::new();
+    template.insert("name".to_string(), name.into());
+    // if there is a reload command included, add that too
+    if let Some(cmd) = reload {
+        template.insert("reload".to_string(), cmd.into());
+    }
+    match output {
+        [] => {}
+        [output] => {
+            template.insert("output".to_string(), (*output).into());
+        }
+        outputs => {
+            template.insert("output".to_string(), outputs.to_vec().into());
+        }
+    }
+    entry("templates", vec![toml::Value::from(template)].into())
+}
+
+/// a [theme.name] table with a palette
+fn theme_entry(name: &str, palette: &str) -> Result<String> {
+    let mut theme = toml::Table::new();
+    theme.insert("palette".to_string(), palette.into());
+    let mut themes = toml::Table::new();
+    themes.insert(name.to_string(), theme.into());
+    entry("theme", themes.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("5s").unwrap(), Duration::from_secs(5));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("1.5").unwrap(), Duration::from_millis(1500));
+        assert!(parse_duration("5h").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn theme_entries_are_escaped() {
+        let entry = theme_entry("my \"dark\" theme", "/palettes/a\\b.yaml").unwrap();
+        let parsed: HashMap<String, HashMap<String, ThemeConfig>> = toml::from_str(&entry).unwrap();
+        let theme = &parsed["theme"]["my \"dark\" theme"];
+        assert_eq!(theme.palette.as_deref(), Some("/palettes/a\\b.yaml"));
+        let entry = theme_entry("forest", "f.yaml").unwrap();
+        assert!(entry.contains("[theme.forest]"));
+    }
+
+    #[test]
+    fn template_entries_parse() {
+        let parsed: HashMap<String, Vec<Template>> =
+            toml::from_str(&template_entry("a.css", Some("echo \"hi\""), &["~/a"]).unwrap())
+                .unwrap();
+        let template = &parsed["templates"][0];
+        assert_eq!(template.name, "a.css");
+        assert_eq!(template.reload.as_deref(), Some("echo \"hi\""));
+        assert_eq!(template.output, ["~/a"]);
+
+        let entry = template_entry("b.conf", None, &["~/b", "$HOME/c"]).unwrap();
+        let parsed: HashMap<String, Vec<Template>> = toml::from_str(&entry).unwrap();
+        assert_eq!(parsed["templates"][0].output, ["~/b", "$HOME/c"]);
+        assert!(parsed["templates"][0].reload.is_none());
+    }
+
+    #[test]
+    fn hook_options() {
+        let hook: Hook = toml::from_str("command = \"x\"\ntimeout = \"1s\"").unwrap();
+        assert!(hook.blocking());
+        assert_eq!(hook.on_failure, OnFailure::Warn);
+        let hook: Hook = toml::from_str("command = \"x\"\non_failure = \"abort\"").unwrap();
+        assert!(hook.blocking());
+        let hook: Hook = toml::from_str("command = \"x\"").unwrap();
+        assert!(!hook.blocking() && !hook.stdout && !hook.stderr);
+        assert_eq!(hook.log_name(2).unwrap(), "hook-2.log");
+        let hook: Hook = toml::from_str("command = \"x\"\nname = \"bar\"").unwrap();
+        assert_eq!(hook.log_name(2).unwrap(), "bar.log");
+        let hook: Hook = toml::from_str("command = \"x\"\nname = \"../bar\"").unwrap();
+        assert!(hook.log_name(0).is_err());
+    }
+}
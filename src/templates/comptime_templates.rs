@@ -10,6 +10,11 @@ pub struct ComptimeTemplate {
     pub filename: &'static str,
     pub content: &'static str,
     pub reload: Option<&'static str>,
+    /// where the template is rendered to, if not the cache dir
+    pub output: &'static [&'static str],
+    /// [[hooks]] installed alongside the template, for apps that take their
+    /// colors over IPC instead of from a file
+    pub hooks: &'static [&'static str],
 }
 
 /// This is where you actually put the templates which are included
@@ -20,6 +25,8 @@ pub static COMPTIME_TEMPLATES: &[ComptimeTemplate] = &[
         filename: "colors-waybar.css",
         content: include_str!("comptime_templates/waybar.css"),
         reload: Some("killall -SIGUSR2 waybar"),
+        output: &[],
+        hooks: &[],
     },
     ComptimeTemplate {
         name: "rofi",
@@ -28,6 +35,150 @@ pub static COMPTIME_TEMPLATES: &[ComptimeTemplate] = &[
         // is included into the program at comptime
         content: include_str!("comptime_templates/rofi.rasi"),
         reload: None,
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "kitty",
+        filename: "colors-kitty.conf",
+        content: include_str!("comptime_templates/kitty.conf"),
+        reload: Some("pkill -USR1 -x kitty"),
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "alacritty",
+        filename: "colors-alacritty.toml",
+        content: include_str!("comptime_templates/alacritty.toml"),
+        // alacritty watches imported files itself
+        reload: None,
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "foot",
+        filename: "colors-foot.ini",
+        content: include_str!("comptime_templates/foot.ini"),
+        // foot has no live reload, new windows read the new colors
+        reload: None,
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "ghostty",
+        filename: "colors-ghostty",
+        content: include_str!("comptime_templates/ghostty"),
+        reload: Some("pkill -USR2 -x ghostty"),
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "wezterm",
+        filename: "colors-wezterm.lua",
+        content: include_str!("comptime_templates/wezterm.lua"),
+        // wezterm reloads once the file is on its watch list, see the template
+        reload: None,
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "hyprland",
+        filename: "colors-hyprland.conf",
+        content: include_str!("comptime_templates/hyprland.conf"),
+        reload: Some("hyprctl reload"),
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "sway",
+        filename: "colors-sway",
+        content: include_str!("comptime_templates/sway"),
+        reload: Some("swaymsg reload"),
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "i3",
+        filename: "colors-i3",
+        content: include_str!("comptime_templates/i3"),
+        reload: Some("i3-msg reload"),
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "river",
+        filename: "colors-river.sh",
+        content: include_str!("comptime_templates/river.sh"),
+        reload: None,
+        output: &[],
+        // river only takes runtime commands, so the live update goes through
+        // hooks and the script is only for river's init
+        hooks: &[
+            "riverctl background-color {{background | 0x}}",
+            "riverctl border-color-focused {{primary | 0x}}",
+            "riverctl border-color-unfocused {{background | 0x}}",
+            "riverctl border-color-urgent {{ansi.red | 0x}}",
+        ],
+    },
+    ComptimeTemplate {
+        name: "niri",
+        filename: "colors-niri.kdl",
+        content: include_str!("comptime_templates/niri.kdl"),
+        // niri watches its config, includes are relative to config.kdl
+        reload: None,
+        output: &["~/.config/niri/colors.kdl"],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "neovim",
+        filename: "colors-neovim.lua",
+        content: include_str!("comptime_templates/neovim.lua"),
+        // running instances pick it up on the next :colorscheme chlorophyll
+        reload: None,
+        output: &["~/.config/nvim/colors/chlorophyll.lua"],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "helix",
+        filename: "colors-helix.toml",
+        content: include_str!("comptime_templates/helix.toml"),
+        // helix reloads its config and theme on SIGUSR1
+        reload: Some("pkill -USR1 -x hx"),
+        output: &["~/.config/helix/themes/chlorophyll.toml"],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "vscode",
+        filename: "colors-vscode.json",
+        content: include_str!("comptime_templates/vscode.json"),
+        // vscode watches theme files of installed extensions
+        reload: None,
+        output: &["~/.vscode/extensions/chlorophyll/themes/chlorophyll-color-theme.json"],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "gtk",
+        filename: "colors-gtk.css",
+        content: include_str!("comptime_templates/gtk.css"),
+        // GTK 4 apps restyle when the color scheme changes, so flip it away and
+        // back. drop this if you dont use GNOME's settings daemon
+        reload: Some(
+            "gsettings set org.gnome.desktop.interface color-scheme default && gsettings set org.gnome.desktop.interface color-scheme prefer-{{mode}}",
+        ),
+        output: &[],
+        hooks: &[],
+    },
+    ComptimeTemplate {
+        name: "qt",
+        filename: "colors-qt.conf",
+        content: include_str!("comptime_templates/qt.conf"),
+        // qt apps only read the palette on startup
+        reload: None,
+        output: &[
+            "~/.config/qt5ct/colors/chlorophyll.conf",
+            "~/.config/qt6ct/colors/chlorophyll.conf",
+        ],
+        hooks: &[],
     },
 ];
 
@@ -63,7 +214,11 @@ impl ComptimeTemplate {
 
         if !force {
             // skip writing it if forcing, assume its already in config
-            Config::append_template_entry(self.filename, self.reload)?;
+            Config::append_template_entry(self.filename, self.reload, self.output)?;
+            for hook in self.hooks {
+                Config::append_hook_entry(hook)?;
+                println!("Added hook: {hook}");
+            }
         }
 
         println!("Installed template to {}", dest.display());
@@ -86,4 +241,62 @@ mod tests {
     fn find_unknown_template() {
         assert!(find_comptime_template("nonexistent").is_none());
     }
+
+    #[test]
+    fn starters_with_fixed_outputs() {
+        for (name, outputs) in [
+            ("niri", 1),
+            ("neovim", 1),
+            ("helix", 1),
+            ("vscode", 1),
+            ("qt", 2),
+        ] {
+            let t = find_comptime_template(name).unwrap();
+            assert_eq!(t.output.len(), outputs, "{name}");
+            // the apps look for these under the home dir, not the cache
+            for output in t.output {
+                let path = paths::expand(output).unwrap();
+                assert!(path.starts_with(std::env::var("HOME").unwrap()), "{output}");
+            }
+        }
+        assert!(find_comptime_template("waybar").unwrap().output.is_empty());
+    }
+
+    #[test]
+    fn starters_render_strictly() {
+        use crate::templates::renderer;
+        use crate::utils::colorspace::Rgb;
+        use crate::utils::palette::{self, LabelOptions, Swatch};
+        use std::path::Path;
+
+        let swatches: Vec<Swatch> = (0..16u8)
+            .map(|i| Swatch {
+                color: Rgb(i * 16, 255 - i * 16, i * 8),
+                coverage: 1.0 / 16.0,
+            })
+            .collect();
+        let labels = palette::assign_labels(&swatches, &LabelOptions::default());
+        let vars = renderer::build_variables(&swatches, &labels, "/wall.png");
+
+        for t in COMPTIME_TEMPLATES {
+            let rendered = renderer::substitute_with_partials(
+                t.content,
+                &vars,
+                Path::new("/nonexistent"),
+                true,
+            )
+            .unwrap_or_else(|e| panic!("{} failed to render: {e:#}", t.name));
+            assert!(!rendered.contains("{{"), "{} left a tag behind", t.name);
+            if let Some(reload) = t.reload {
+                renderer::substitute_with_partials(reload, &vars, Path::new("/nonexistent"), true)
+                    .unwrap_or_else(|e| panic!("{} reload failed to render: {e:#}", t.name));
+            }
+            for hook in t.hooks {
+                assert!(
+                    renderer::substitute(hook, &vars).is_ok(),
+                    "{hook} failed to render"
+                );
+            }
+        }
+    }
 }
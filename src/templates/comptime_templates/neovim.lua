@@ -0,0 +1,126 @@
+-- Chlorophyll — Neovim colorscheme
+-- Rendered to ~/.config/nvim/colors/chlorophyll.lua, load it with
+--   :colorscheme chlorophyll
+-- or vim.cmd.colorscheme("chlorophyll") in your init.lua
+
+vim.cmd("highlight clear")
+if vim.g.syntax_on then
+  vim.cmd("syntax reset")
+end
+vim.g.colors_name = "chlorophyll"
+vim.o.background = "{{mode}}"
+
+local c = {
+  bg = "{{background}}",
+  fg = "{{foreground}}",
+  primary = "{{primary}}",
+  secondary = "{{secondary}}",
+  surface = "{{syntax.surface}}",
+  overlay = "{{syntax.overlay}}",
+  border = "{{syntax.border}}",
+  selection = "{{syntax.selection}}",
+  cursorline = "{{syntax.cursorline}}",
+  comment = "{{syntax.comment}}",
+  keyword = "{{syntax.keyword}}",
+  func = "{{syntax.function}}",
+  string = "{{syntax.string}}",
+  number = "{{syntax.number}}",
+  type = "{{syntax.type}}",
+  constant = "{{syntax.constant}}",
+  operator = "{{syntax.operator}}",
+  variable = "{{syntax.variable}}",
+  punctuation = "{{syntax.punctuation}}",
+  red = "{{ansi.red}}",
+  yellow = "{{ansi.yellow}}",
+  green = "{{ansi.green}}",
+  blue = "{{ansi.blue}}",
+  cyan = "{{ansi.cyan}}",
+}
+
+local groups = {
+  -- editor
+  Normal = { fg = c.fg, bg = c.bg },
+  NormalFloat = { fg = c.fg, bg = c.surface },
+  FloatBorder = { fg = c.border, bg = c.surface },
+  Cursor = { fg = c.bg, bg = c.primary },
+  CursorLine = { bg = c.cursorline },
+  CursorLineNr = { fg = c.primary, bold = true },
+  LineNr = { fg = c.border },
+  SignColumn = { bg = c.bg },
+  ColorColumn = { bg = c.surface },
+  Visual = { bg = c.selection },
+  Search = { fg = c.bg, bg = c.secondary },
+  IncSearch = { fg = c.bg, bg = c.primary },
+  CurSearch = { link = "IncSearch" },
+  MatchParen = { bg = c.overlay, bold = true },
+  Pmenu = { fg = c.fg, bg = c.surface },
+  PmenuSel = { bg = c.selection },
+  PmenuSbar = { bg = c.overlay },
+  PmenuThumb = { bg = c.border },
+  StatusLine = { fg = c.fg, bg = c.overlay },
+  StatusLineNC = { fg = c.comment, bg = c.surface },
+  TabLine = { fg = c.comment, bg = c.surface },
+  TabLineSel = { fg = c.fg, bg = c.bg, bold = true },
+  TabLineFill = { bg = c.surface },
+  WinSeparator = { fg = c.border },
+  Folded = { fg = c.comment, bg = c.surface },
+  NonText = { fg = c.border },
+  Whitespace = { fg = c.overlay },
+  Title = { fg = c.primary, bold = true },
+  Directory = { fg = c.func },
+  ErrorMsg = { fg = c.red },
+  WarningMsg = { fg = c.yellow },
+
+  -- syntax
+  Comment = { fg = c.comment, italic = true },
+  Constant = { fg = c.constant },
+  String = { fg = c.string },
+  Character = { fg = c.string },
+  Number = { fg = c.number },
+  Boolean = { fg = c.number },
+  Float = { fg = c.number },
+  Identifier = { fg = c.variable },
+  Function = { fg = c.func },
+  Statement = { fg = c.keyword },
+  Keyword = { fg = c.keyword },
+  Conditional = { fg = c.keyword },
+  Repeat = { fg = c.keyword },
+  Operator = { fg = c.operator },
+  PreProc = { fg = c.keyword },
+  Type = { fg = c.type },
+  Special = { fg = c.operator },
+  Delimiter = { fg = c.punctuation },
+  Todo = { fg = c.bg, bg = c.primary, bold = true },
+
+  -- treesitter
+  ["@variable"] = { fg = c.variable },
+  ["@variable.member"] = { fg = c.variable },
+  ["@property"] = { fg = c.variable },
+  ["@function.builtin"] = { fg = c.func, italic = true },
+  ["@constructor"] = { fg = c.type },
+  ["@punctuation"] = { fg = c.punctuation },
+
+  -- diagnostics and diffs
+  DiagnosticError = { fg = c.red },
+  DiagnosticWarn = { fg = c.yellow },
+  DiagnosticInfo = { fg = c.blue },
+  DiagnosticHint = { fg = c.cyan },
+  DiagnosticUnderlineError = { sp = c.red, undercurl = true },
+  DiagnosticUnderlineWarn = { sp = c.yellow, undercurl = true },
+  DiffAdd = { fg = c.green },
+  DiffChange = { fg = c.yellow },
+  DiffDelete = { fg = c.red },
+  DiffText = { bg = c.overlay },
+  Added = { fg = c.green },
+  Changed = { fg = c.yellow },
+  Removed = { fg = c.red },
+}
+
+for group, hl in pairs(groups) do
+  vim.api.nvim_set_hl(0, group, hl)
+end
+
+-- terminal buffers
+{{#each ansi}}
+vim.g.terminal_color_{{@index}} = "{{this}}"
+{{/each}}
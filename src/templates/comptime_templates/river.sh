@@ -0,0 +1,13 @@
+#!/bin/sh
+# Chlorophyll — river colors
+# Rendered output goes to ~/.cache/chlorophyll/colors-river.sh
+#
+# river has no config file, only riverctl commands. The [[hooks]] installed with
+# this template send them on every theme change. Run this script from your
+# river init so the colors are also there after a restart:
+#   sh ~/.cache/chlorophyll/colors-river.sh
+
+riverctl background-color {{background | 0x}}
+riverctl border-color-focused {{primary | 0x}}
+riverctl border-color-unfocused {{background | 0x}}
+riverctl border-color-urgent {{ansi.red | 0x}}
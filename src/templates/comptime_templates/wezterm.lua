@@ -0,0 +1,43 @@
+-- Chlorophyll — WezTerm colors
+-- Rendered output goes to ~/.cache/chlorophyll/colors-wezterm.lua
+--
+-- Load it from your wezterm.lua:
+--   local colors_path = wezterm.home_dir .. "/.cache/chlorophyll/colors-wezterm.lua"
+--   wezterm.add_to_config_reload_watch_list(colors_path)
+--   config.colors = dofile(colors_path)
+-- With the file on the watch list WezTerm reloads on its own when it changes
+
+return {
+  foreground = "{{foreground}}",
+  background = "{{background}}",
+
+  cursor_bg = "{{primary}}",
+  cursor_fg = "{{background}}",
+  cursor_border = "{{primary}}",
+
+  selection_fg = "{{background}}",
+  selection_bg = "{{primary}}",
+
+  split = "{{ansi.bright_black}}",
+
+  ansi = {
+    "{{ansi.black}}",
+    "{{ansi.red}}",
+    "{{ansi.green}}",
+    "{{ansi.yellow}}",
+    "{{ansi.blue}}",
+    "{{ansi.magenta}}",
+    "{{ansi.cyan}}",
+    "{{ansi.white}}",
+  },
+  brights = {
+    "{{ansi.bright_black}}",
+    "{{ansi.bright_red}}",
+    "{{ansi.bright_green}}",
+    "{{ansi.bright_yellow}}",
+    "{{ansi.bright_blue}}",
+    "{{ansi.bright_magenta}}",
+    "{{ansi.bright_cyan}}",
+    "{{ansi.bright_white}}",
+  },
+}
@@ -0,0 +1,245 @@
+use crate::utils::colorspace::Rgb;
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+
+/// the output formats a color can be turned into, also used for the fixed
+/// {{colorN.strip}} style suffix variables
+pub const FORMATS: [&str; 10] = [
+    "hex", "strip", "0x", "rgb", "rgba", "red", "green", "blue", "hsv", "oklch",
+];
+
+/// filters that take a color and give back a changed color
+const COLOR_FILTERS: [&str; 8] = [
+    "lighten",
+    "darken",
+    "saturate",
+    "desaturate",
+    "rotate",
+    "complement",
+    "alpha",
+    "mix",
+];
+
+/// what flows from one filter into the next
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// a color and its opacity in [0-1]
+    Color(Rgb, f64),
+    Text(String),
+}
+
+impl Value {
+    /// the color this value holds. text is parsed as hex so plain variables like
+    /// {{color0}} can be piped straight into color filters
+    fn color(&self, filter: &str) -> Result<(Rgb, f64)> {
+        match self {
+            Value::Color(c, alpha) => Ok((*c, *alpha)),
+            Value::Text(s) => s
+                .parse::<Rgb>()
+                .map(|c| (c, 1.0))
+                .with_context(|| format!("filter '{filter}' needs a color, got '{s}'")),
+        }
+    }
+
+    /// what ends up in the rendered file. colors without a format filter are hex
+    pub fn render(self) -> String {
+        match self {
+            Value::Color(c, alpha) => format(c, alpha, "hex").unwrap_or_default(),
+            Value::Text(s) => s,
+        }
+    }
+}
+
+/// write a color out in one of FORMATS. the hex based formats only get an alpha
+/// byte when the color is actually transparent, so opaque colors stay 6 digits
+pub fn format(c: Rgb, alpha: f64, format: &str) -> Option<String> {
+    let alpha_byte = if alpha < 1.0 {
+        format!("{:02x}", (alpha * 255.0).round() as u8)
+    } else {
+        String::new()
+    };
+    // round the alpha so 0.8 doesnt come out as 0.800000001
+    let alpha = (alpha * 100.0).round() / 100.0;
+
+    let out = match format {
+        // Base css/gtk hex code
+        "hex" => format!("{}{alpha_byte}", c.hex()),
+        // Some tools want hex but without the #
+        "strip" => format!("{:02x}{:02x}{:02x}{alpha_byte}", c.0, c.1, c.2),
+        // 0x prefixed hex for things like river which require it
+        "0x" => format!("0x{:02x}{:02x}{:02x}{alpha_byte}", c.0, c.1, c.2),
+        // CSS rgb values
+        "rgb" => format!("{},{},{}", c.0, c.1, c.2),
+        "rgba" => format!("{},{},{},{alpha}", c.0, c.1, c.2),
+        // float based channels for tools like sway which want 0-1
+        "red" => format!("{:.4}", c.0 as f64 / 255.0),
+        "green" => format!("{:.4}", c.1 as f64 / 255.0),
+        "blue" => format!("{:.4}", c.2 as f64 / 255.0),
+        // hue in degrees, saturation and value in percent, for hsv color pickers
+        "hsv" => {
+            let hsv = c.hsv();
+            format!(
+                "{:.0},{:.0},{:.0}",
+                hsv.hue * 360.0,
+                hsv.saturation * 100.0,
+                hsv.value * 100.0
+            )
+        }
+        // css color 4 oklch(), with the alpha only when it isnt opaque
+        "oklch" => {
+            let lch = c.oklch();
+            let alpha = if alpha < 1.0 {
+                format!(" / {alpha}")
+            } else {
+                String::new()
+            };
+            format!(
+                "oklch({:.1}% {:.3} {:.1}{alpha})",
+                lch.l * 100.0,
+                lch.chroma,
+                lch.hue * 360.0
+            )
+        }
+        _ => return None,
+    };
+    Some(out)
+}
+
+/// the single numeric argument most filters take
+fn number(filter: &str, args: &[&str]) -> Result<f64> {
+    let [arg] = args else {
+        bail!("filter '{filter}' takes 1 argument, got {}", args.len());
+    };
+    arg.parse()
+        .with_context(|| format!("filter '{filter}' expects a number, got '{arg}'"))
+}
+
+fn no_args(filter: &str, args: &[&str]) -> Result<()> {
+    if !args.is_empty() {
+        bail!("filter '{filter}' takes no arguments");
+    }
+    Ok(())
+}
+
+/// run one filter on a value
+fn apply(
+    value: Value,
+    filter: &str,
+    args: &[&str],
+    vars: &HashMap<String, String>,
+) -> Result<Value> {
+    if !FORMATS.contains(&filter) && !COLOR_FILTERS.contains(&filter) {
+        bail!("unknown filter '{filter}'");
+    }
+
+    let (c, alpha) = value.color(filter)?;
+    if let Some(out) = format(c, alpha, filter) {
+        no_args(filter, args)?;
+        return Ok(Value::Text(out));
+    }
+
+    let color = match filter {
+        "lighten" => c.lighten(number(filter, args)?),
+        "darken" => c.darken(number(filter, args)?),
+        "saturate" => c.saturate(number(filter, args)?),
+        "desaturate" => c.desaturate(number(filter, args)?),
+        "rotate" => c.rotate_hue(number(filter, args)?),
+        "complement" => {
+            no_args(filter, args)?;
+            c.complement()
+        }
+        "alpha" => return Ok(Value::Color(c, number(filter, args)?.clamp(0.0, 1.0))),
+        // mix with another variable or a hex literal, half and half by default
+        _ => {
+            let (other, weight) = match args {
+                [other] => (*other, 0.5),
+                [other, weight] => (*other, number(filter, &[weight])?),
+                _ => bail!("filter 'mix' takes a color and an optional weight"),
+            };
+            let other = vars.get(other).map(String::as_str).unwrap_or(other);
+            let other = other
+                .parse::<Rgb>()
+                .with_context(|| format!("filter 'mix' needs a color, got '{other}'"))?;
+            c.mix(&other, weight)
+        }
+    };
+    Ok(Value::Color(color, alpha))
+}
+
+/// evaluate the inside of a {{ }} tag: a variable followed by any number of
+/// filters, like `background | darken 0.1 | hex`. returns None when the
+/// variable doesnt exist
+pub fn evaluate(expr: &str, vars: &HashMap<String, String>) -> Result<Option<String>> {
+    let mut stages = expr.split('|').map(str::trim);
+    let key = stages.next().unwrap_or_default();
+    let Some(raw) = vars.get(key) else {
+        return Ok(None);
+    };
+
+    let mut value = Value::Text(raw.clone());
+    for stage in stages {
+        let mut parts = stage.split_whitespace();
+        let Some(filter) = parts.next() else {
+            bail!("missing filter after '|'");
+        };
+        let args: Vec<&str> = parts.collect();
+        value = apply(value, filter, &args, vars)?;
+    }
+    Ok(Some(value.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<String, String> {
+        let mut vars = HashMap::new();
+        vars.insert("background".to_string(), "#202020".to_string());
+        vars.insert("primary".to_string(), "#ff0000".to_string());
+        vars.insert("mode".to_string(), "dark".to_string());
+        vars
+    }
+
+    fn eval(expr: &str) -> String {
+        evaluate(expr, &vars()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(eval("primary | strip"), "ff0000");
+        assert_eq!(eval("primary | 0x"), "0xff0000");
+        assert_eq!(eval("primary|rgb"), "255,0,0");
+        assert_eq!(eval("primary | alpha 0.8 | rgba"), "255,0,0,0.8");
+        assert_eq!(eval("primary | alpha 0.5"), "#ff000080");
+        assert_eq!(eval("primary | hsv"), "0,100,100");
+        assert_eq!(eval("primary | oklch"), "oklch(62.8% 0.258 29.2)");
+        assert_eq!(
+            eval("primary | alpha 0.5 | oklch"),
+            "oklch(62.8% 0.258 29.2 / 0.5)"
+        );
+    }
+
+    #[test]
+    fn color_filters() {
+        assert_eq!(eval("background | lighten 1"), "#ffffff");
+        assert_eq!(eval("primary | darken 0.5 | hex"), "#000000");
+        assert_eq!(eval("primary | mix background 0"), "#ff0000");
+        assert_eq!(eval("primary | mix #202020 1"), "#202020");
+        assert_eq!(eval("primary | complement"), "#00ffff");
+    }
+
+    #[test]
+    fn bad_filters_are_errors() {
+        let err = evaluate("primary | glow 2", &vars()).unwrap_err();
+        assert_eq!(err.to_string(), "unknown filter 'glow'");
+        assert!(evaluate("primary | darken", &vars()).is_err());
+        assert!(evaluate("primary | darken lots", &vars()).is_err());
+        assert!(evaluate("mode | darken 0.1", &vars()).is_err());
+        assert!(evaluate("primary |", &vars()).is_err());
+    }
+
+    #[test]
+    fn unknown_variable() {
+        assert_eq!(evaluate("nope | darken 0.1", &vars()).unwrap(), None);
+    }
+}
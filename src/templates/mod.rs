@@ -1,2 +1,4 @@
 pub mod comptime_templates;
+pub mod filters;
+pub mod parser;
 pub mod renderer;
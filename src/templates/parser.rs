@@ -0,0 +1,325 @@
+use anyhow::{Error, Result, anyhow};
+use std::fmt;
+
+/// a piece of a parsed template. blocks hold the nodes between their open and
+/// close tags. offsets point at the {{ of the tag, for error messages
+#[derive(Debug, PartialEq)]
+pub enum Node<'a> {
+    Text(&'a str),
+    /// {{key | filters}}. raw is the whole tag so unknown keys can be kept as is
+    Expr {
+        expr: &'a str,
+        raw: &'a str,
+        offset: usize,
+    },
+    /// {{#each list}}...{{/each}}
+    Each {
+        list: &'a str,
+        body: Vec<Node<'a>>,
+        offset: usize,
+    },
+    /// a {{ that is never closed, and everything after it
+    Unclosed { text: &'a str, offset: usize },
+    /// {{> name}}, another template pulled in from the partials directory
+    Partial {
+        name: &'a str,
+        offset: usize,
+    },
+    /// {{#if cond}}...{{else}}...{{/if}}
+    If {
+        cond: &'a str,
+        then: Vec<Node<'a>>,
+        otherwise: Vec<Node<'a>>,
+        offset: usize,
+    },
+}
+
+/// a {{ }} tag before blocks are matched up
+struct Tag<'a> {
+    inner: &'a str,
+    raw: &'a str,
+    offset: usize,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Tag(Tag<'a>),
+    Unclosed(&'a str, usize),
+}
+
+/// how a run of nodes ended
+enum Ending {
+    Eof,
+    Else,
+    Close,
+}
+
+/// line and column (both 1 based) of a byte offset
+pub fn position(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let col = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, col)
+}
+
+/// an error at a specific place in a template. kept as its own type so
+/// `chlorophyll check` can print it as file:line:col
+#[derive(Debug)]
+pub struct TemplateError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, col {}: {}", self.line, self.col, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// attach where in the template an error happened
+pub fn error_at(input: &str, offset: usize, e: impl Into<Error>) -> Error {
+    let (line, col) = position(input, offset);
+    Error::new(TemplateError {
+        line,
+        col,
+        // {:#} keeps the whole context chain, errors from inside partials have one
+        message: format!("{:#}", e.into()),
+    })
+}
+
+/// tags that dont output anything on their own line
+fn is_block(inner: &str) -> bool {
+    inner.starts_with(['#', '/', '>']) || inner == "else"
+}
+
+/// split the template into text and tags
+///
+/// block tags and includes that sit on a line of their own take the whole line
+/// with them, so wrapping lines in {{#each}} or {{#if}} doesnt leave blank lines behind
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = input[pos..].find("{{") {
+        let start = pos + found;
+        let Some(len) = input[start + 2..].find("}}") else {
+            if start > pos {
+                tokens.push(Token::Text(&input[pos..start]));
+            }
+            tokens.push(Token::Unclosed(&input[start..], start));
+            return tokens;
+        };
+        let end = start + 2 + len + 2;
+        let inner = input[start + 2..end - 2].trim();
+
+        let (mut text_end, mut next) = (start, end);
+        if is_block(inner) {
+            let line_start = input[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = input[end..].find('\n').map_or(input.len(), |i| end + i + 1);
+            if input[line_start..start].trim().is_empty() && input[end..line_end].trim().is_empty()
+            {
+                (text_end, next) = (line_start, line_end);
+            }
+        }
+
+        if text_end > pos {
+            tokens.push(Token::Text(&input[pos..text_end]));
+        }
+        tokens.push(Token::Tag(Tag {
+            inner,
+            raw: &input[start..end],
+            offset: start,
+        }));
+        pos = next;
+    }
+
+    if pos < input.len() {
+        tokens.push(Token::Text(&input[pos..]));
+    }
+    tokens
+}
+
+/// collect nodes until the block named by `open` ends, or the input does
+fn parse_nodes<'a>(
+    input: &'a str,
+    tokens: &mut impl Iterator<Item = Token<'a>>,
+    open: Option<(&str, usize)>,
+) -> Result<(Vec<Node<'a>>, Ending)> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            }
+            Token::Unclosed(text, offset) => {
+                nodes.push(Node::Unclosed { text, offset });
+                continue;
+            }
+            Token::Tag(tag) => tag,
+        };
+        let offset = tag.offset;
+
+        if let Some(name) = tag.inner.strip_prefix('/') {
+            let name = name.trim();
+            return match open {
+                Some((expected, _)) if expected == name => Ok((nodes, Ending::Close)),
+                Some((expected, _)) => Err(error_at(
+                    input,
+                    offset,
+                    anyhow!("expected {{{{/{expected}}}}}, found {{{{/{name}}}}}"),
+                )),
+                None => Err(error_at(
+                    input,
+                    offset,
+                    anyhow!("{{{{/{name}}}}} without an opening block"),
+                )),
+            };
+        }
+
+        if tag.inner == "else" {
+            return match open {
+                Some(("if", _)) => Ok((nodes, Ending::Else)),
+                _ => Err(error_at(
+                    input,
+                    offset,
+                    anyhow!("{{{{else}}}} outside of an {{{{#if}}}}"),
+                )),
+            };
+        }
+
+        if let Some(name) = tag.inner.strip_prefix('>') {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(error_at(
+                    input,
+                    offset,
+                    anyhow!("{{{{>}}}} is missing a partial name"),
+                ));
+            }
+            nodes.push(Node::Partial { name, offset });
+            continue;
+        }
+
+        let Some(block) = tag.inner.strip_prefix('#') else {
+            nodes.push(Node::Expr {
+                expr: tag.inner,
+                raw: tag.raw,
+                offset,
+            });
+            continue;
+        };
+
+        let (name, arg) = block.split_once(char::is_whitespace).unwrap_or((block, ""));
+        let arg = arg.trim();
+        if arg.is_empty() {
+            return Err(error_at(
+                input,
+                offset,
+                anyhow!("{{{{#{name}}}}} is missing its argument"),
+            ));
+        }
+
+        match name {
+            "each" => {
+                let (body, _) = parse_nodes(input, tokens, Some(("each", offset)))?;
+                nodes.push(Node::Each {
+                    list: arg,
+                    body,
+                    offset,
+                });
+            }
+            "if" => {
+                let (then, ending) = parse_nodes(input, tokens, Some(("if", offset)))?;
+                let otherwise = match ending {
+                    Ending::Else => {
+                        let (otherwise, ending) = parse_nodes(input, tokens, Some(("if", offset)))?;
+                        if let Ending::Else = ending {
+                            return Err(error_at(
+                                input,
+                                offset,
+                                anyhow!("{{{{#if}}}} has more than one {{{{else}}}}"),
+                            ));
+                        }
+                        otherwise
+                    }
+                    _ => Vec::new(),
+                };
+                nodes.push(Node::If {
+                    cond: arg,
+                    then,
+                    otherwise,
+                    offset,
+                });
+            }
+            _ => return Err(error_at(input, offset, anyhow!("unknown block '#{name}'"))),
+        }
+    }
+
+    match open {
+        Some((name, offset)) => Err(error_at(
+            input,
+            offset,
+            anyhow!("{{{{#{name}}}}} is never closed"),
+        )),
+        None => Ok((nodes, Ending::Eof)),
+    }
+}
+
+/// parse a template into nodes, matching up every block with its close tag
+pub fn parse(input: &str) -> Result<Vec<Node<'_>>> {
+    let mut tokens = tokenize(input).into_iter();
+    Ok(parse_nodes(input, &mut tokens, None)?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standalone_block_tags_take_their_line() {
+        let nodes = parse("a\n  {{#if x}}\nb\n{{/if}}\nc").unwrap();
+        assert_eq!(
+            nodes,
+            vec![
+                Node::Text("a\n"),
+                Node::If {
+                    cond: "x",
+                    then: vec![Node::Text("b\n")],
+                    otherwise: vec![],
+                    offset: 4,
+                },
+                Node::Text("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_blocks_keep_whitespace() {
+        let nodes = parse("{{#each colors}} {{this}},{{/each}}").unwrap();
+        let Node::Each { body, .. } = &nodes[0] else {
+            panic!("expected an each block");
+        };
+        assert_eq!(body[0], Node::Text(" "));
+        assert_eq!(body[2], Node::Text(","));
+    }
+
+    #[test]
+    fn block_errors_have_positions() {
+        let err = parse("a\n{{#each colors}}\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2, col 1: {{#each}} is never closed");
+        let err = parse("{{#if x}}{{/each}}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1, col 10: expected {{/if}}, found {{/each}}"
+        );
+        assert!(parse("{{else}}").is_err());
+        assert!(parse("{{#if x}}a{{else}}b{{else}}c{{/if}}").is_err());
+        assert!(parse("{{#loop x}}{{/loop}}").is_err());
+        assert!(parse("{{> }}").is_err());
+    }
+}
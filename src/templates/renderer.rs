@@ -1,131 +1,468 @@
 use crate::config::Template;
+use crate::templates::filters;
+use crate::templates::parser::{self, Node};
+use crate::utils::ansi;
 use crate::utils::colorspace::Rgb;
-use crate::utils::palette;
+use crate::utils::palette::{LabeledColors, Swatch};
 use crate::utils::paths;
-use anyhow::{Context, Result};
+use crate::utils::syntax;
+use anyhow::{Context, Result, anyhow, bail};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// make all color format variants for a given prefix (hex, strip, rgb, red, green, blue).
+/// the same formats are available as filters, {{primary.strip}} is {{primary | strip}}
 fn insert_color_vars(vars: &mut HashMap<String, String>, prefix: &str, c: &Rgb) {
     // Base css/gtk hex code
     vars.insert(prefix.to_string(), c.hex());
-    // Some tools want hex but without the #
-    vars.insert(
-        format!("{prefix}.strip"),
-        format!("{:02x}{:02x}{:02x}", c.0, c.1, c.2),
-    );
-    // CSS rgb values
-    vars.insert(format!("{prefix}.rgb"), format!("{},{},{}", c.0, c.1, c.2));
-    // 0x prefixed hex for things like river which require it
-    vars.insert(
-        format!("{prefix}.0x"),
-        format!("0x{:02x}{:02x}{:02x}", c.0, c.1, c.2),
-    );
-    // float based channels for tools like sway which want 0-1
-    vars.insert(
-        format!("{prefix}.red"),
-        format!("{:.4}", c.0 as f64 / 255.0),
-    );
-    vars.insert(
-        format!("{prefix}.green"),
-        format!("{:.4}", c.1 as f64 / 255.0),
-    );
-    vars.insert(
-        format!("{prefix}.blue"),
-        format!("{:.4}", c.2 as f64 / 255.0),
-    );
+    for format in ["strip", "rgb", "0x", "red", "green", "blue"] {
+        if let Some(value) = filters::format(*c, 1.0, format) {
+            vars.insert(format!("{prefix}.{format}"), value);
+        }
+    }
 }
 
 /// build vars hashmap with different color formats for different tools
-pub fn build_variables(colors: &[Rgb], wallpaper_path: &str) -> HashMap<String, String> {
+pub fn build_variables(
+    colors: &[Swatch],
+    labels: &LabeledColors,
+    wallpaper_path: &str,
+) -> HashMap<String, String> {
     let mut vars = HashMap::new();
 
-    for (i, c) in colors.iter().enumerate() {
-        insert_color_vars(&mut vars, &format!("color{i}"), c);
+    for (i, s) in colors.iter().enumerate() {
+        insert_color_vars(&mut vars, &format!("color{i}"), &s.color);
+        // share of the wallpaper this color covers, in [0-1]
+        vars.insert(format!("color{i}.coverage"), format!("{:.4}", s.coverage));
     }
 
     // semantic labels derived from the palette
-    let labels = palette::assign_labels(colors);
     insert_color_vars(&mut vars, "background", &labels.background);
     insert_color_vars(&mut vars, "foreground", &labels.foreground);
     insert_color_vars(&mut vars, "primary", &labels.primary);
     insert_color_vars(&mut vars, "secondary", &labels.secondary);
 
+    // terminal colors mapped by hue, so ansi.red is actually red
+    let ansi = ansi::ansi_colors(colors, labels);
+    for (i, name) in ansi::ANSI_NAMES.iter().enumerate() {
+        insert_color_vars(&mut vars, &format!("ansi.{name}"), &ansi.normal[i]);
+        insert_color_vars(&mut vars, &format!("ansi.bright_{name}"), &ansi.bright[i]);
+    }
+
+    // editor roles, for neovim/helix/vscode style themes
+    for (name, c) in syntax::syntax_colors(colors, labels) {
+        insert_color_vars(&mut vars, &format!("syntax.{name}"), &c);
+    }
+
+    // "dark" or "light", already resolved if the config says "auto"
+    vars.insert("mode".to_string(), labels.mode.as_str().to_string());
+
     // insert the wallpaper as a var for tools like lock screens
     vars.insert("wallpaper".to_string(), wallpaper_path.to_string());
     vars
 }
 
+/// the variables as environment variables, sorted by name. `color0.strip`
+/// becomes CHLOROPHYLL_COLOR0_STRIP
+pub fn env_vars(vars: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = vars
+        .iter()
+        .map(|(key, value)| {
+            let name = key.to_uppercase().replace('.', "_");
+            (format!("CHLOROPHYLL_{name}"), value.clone())
+        })
+        .collect();
+    env.sort();
+    env
+}
+
+/// the keys a {{#each}} block walks over, one prefix per item. None if the list
+/// doesnt exist
+fn list_items(list: &str, vars: &HashMap<String, String>) -> Option<Vec<String>> {
+    let items = match list {
+        // as many as the palette has, so templates dont depend on palette_size
+        "colors" => (0..)
+            .map(|i| format!("color{i}"))
+            .take_while(|key| vars.contains_key(key))
+            .collect(),
+        // the 16 terminal colors in slot order, normal then bright
+        "ansi" => ansi::ANSI_NAMES
+            .iter()
+            .map(|name| format!("ansi.{name}"))
+            .chain(
+                ansi::ANSI_NAMES
+                    .iter()
+                    .map(|name| format!("ansi.bright_{name}")),
+            )
+            .filter(|key| vars.contains_key(key))
+            .collect(),
+        _ => return None,
+    };
+    Some(items)
+}
+
+/// the variables inside one {{#each}} iteration. everything under the item's
+/// prefix is also reachable through `this`, so {{this.strip}} is {{color3.strip}}
+fn item_scope(
+    vars: &HashMap<String, String>,
+    prefix: &str,
+    index: usize,
+    len: usize,
+) -> HashMap<String, String> {
+    let mut scope = vars.clone();
+    for (key, value) in vars {
+        if let Some(suffix) = key.strip_prefix(prefix)
+            && (suffix.is_empty() || suffix.starts_with('.'))
+        {
+            scope.insert(format!("this{suffix}"), value.clone());
+        }
+    }
+    scope.insert("@index".to_string(), index.to_string());
+    scope.insert("@first".to_string(), (index == 0).to_string());
+    scope.insert("@last".to_string(), (index + 1 == len).to_string());
+    scope
+}
+
+/// a variable name or a quoted string literal in a condition
+fn operand<'a>(token: &'a str, vars: &'a HashMap<String, String>) -> Result<&'a str> {
+    let token = token.trim();
+    for quote in ['"', '\''] {
+        if let Some(literal) = token
+            .strip_prefix(quote)
+            .and_then(|t| t.strip_suffix(quote))
+        {
+            return Ok(literal);
+        }
+    }
+    vars.get(token)
+        .map(String::as_str)
+        .ok_or_else(|| anyhow!("unknown variable '{token}' in condition"))
+}
+
+/// evaluate an {{#if}} condition. either `a == b`, `a != b` or a single variable,
+/// which is true unless it is empty, "false" or "0"
+fn condition(cond: &str, vars: &HashMap<String, String>) -> Result<bool> {
+    match split_comparison(cond) {
+        Some((a, "!=", b)) => Ok(operand(a, vars)? != operand(b, vars)?),
+        Some((a, _, b)) => Ok(operand(a, vars)? == operand(b, vars)?),
+        None => {
+            let value = operand(cond, vars)?;
+            Ok(!matches!(value, "" | "false" | "0"))
+        }
+    }
+}
+
+/// split at the first == or != outside of a quoted literal, so
+/// `x == "a==b"` compares x against a==b
+fn split_comparison(cond: &str) -> Option<(&str, &str, &str)> {
+    let mut quote = None;
+    for (i, c) in cond.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => {
+                let op = &cond[i..];
+                if op.starts_with("==") || op.starts_with("!=") {
+                    return Some((&cond[..i], &cond[i..i + 2], &cond[i + 2..]));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// state shared by everything rendered from one template. `active` holds the
+/// partials being rendered right now, innermost last, so an include cycle is
+/// reported instead of recursing forever
+struct Renderer<'a> {
+    /// where {{> name}} includes come from, None outside of template files
+    partials_dir: Option<&'a Path>,
+    active: Vec<String>,
+    /// unknown variables and unterminated {{ are errors instead of warnings
+    strict: bool,
+    /// when set, errors are collected here and rendering carries on, so
+    /// `chlorophyll check` can report all of them at once
+    errors: Option<Vec<anyhow::Error>>,
+}
+
+impl Renderer<'_> {
+    /// an exact file name wins, otherwise a file with that stem, so {{> colors}}
+    /// finds partials/colors.css
+    fn find_partial(&self, name: &str) -> Result<PathBuf> {
+        let Some(dir) = self.partials_dir else {
+            bail!("partials like {{{{> {name}}}}} only work in template files");
+        };
+        // partials are file names in the partials dir, not paths out of it
+        if name.contains('/') || name == "." || name == ".." {
+            bail!("partial '{name}' must be a file name in {}", dir.display());
+        }
+        let exact = dir.join(name);
+        if exact.is_file() {
+            return Ok(exact);
+        }
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.file_stem().is_some_and(|stem| stem == name))
+            // read_dir order is arbitrary, keep the pick stable
+            .min()
+            .with_context(|| format!("partial '{name}' not found in {}", dir.display()))
+    }
+
+    fn render_partial(
+        &mut self,
+        name: &str,
+        vars: &HashMap<String, String>,
+        result: &mut String,
+    ) -> Result<()> {
+        if self.active.iter().any(|active| active == name) {
+            bail!("partial cycle: {} -> {name}", self.active.join(" -> "));
+        }
+        let path = self.find_partial(name)?;
+        let source = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read partial: {}", path.display()))?;
+
+        // errors inside the partial are reported once, at the {{> name}}
+        let collected = self.errors.take();
+        self.active.push(name.to_string());
+        let rendered = parser::parse(&source)
+            .and_then(|nodes| self.render_nodes(&nodes, &source, vars, result));
+        self.active.pop();
+        self.errors = collected;
+        rendered.with_context(|| format!("in partial '{name}'"))
+    }
+
+    fn render_nodes(
+        &mut self,
+        nodes: &[Node],
+        input: &str,
+        vars: &HashMap<String, String>,
+        result: &mut String,
+    ) -> Result<()> {
+        for node in nodes {
+            if let Err(e) = self.render_node(node, input, vars, result) {
+                match &mut self.errors {
+                    Some(errors) => errors.push(e),
+                    None => return Err(e),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn render_node(
+        &mut self,
+        node: &Node,
+        input: &str,
+        vars: &HashMap<String, String>,
+        result: &mut String,
+    ) -> Result<()> {
+        match node {
+            Node::Text(text) => result.push_str(text),
+            Node::Expr { expr, raw, offset } => {
+                // if that key exists in the valid keys hashmap...
+                match filters::evaluate(expr, vars)
+                    .map_err(|e| parser::error_at(input, *offset, e))?
+                {
+                    // push the val from the key into the result
+                    Some(val) => result.push_str(&val),
+                    None if self.strict => {
+                        let key = expr.split('|').next().unwrap_or_default().trim();
+                        return Err(parser::error_at(
+                            input,
+                            *offset,
+                            anyhow!("unknown variable '{key}'"),
+                        ));
+                    }
+                    None => {
+                        // {{ = literal {. So this prints \{\{key\}\}
+                        eprintln!("warning: unknown template variable '{{{{{expr}}}}}'");
+                        // keep the entire invalid key for the user to debug
+                        result.push_str(raw);
+                    }
+                }
+            }
+            Node::Unclosed { text, offset } => {
+                if self.strict {
+                    return Err(parser::error_at(
+                        input,
+                        *offset,
+                        anyhow!("unterminated {{{{, expected }}}}"),
+                    ));
+                }
+                // unclosed {{ — pass through literally
+                result.push_str(text);
+            }
+            Node::Each { list, body, offset } => {
+                let items = list_items(list, vars).ok_or_else(|| {
+                    parser::error_at(
+                        input,
+                        *offset,
+                        anyhow!("unknown list '{list}', expected 'colors' or 'ansi'"),
+                    )
+                })?;
+                for (i, prefix) in items.iter().enumerate() {
+                    let scope = item_scope(vars, prefix, i, items.len());
+                    self.render_nodes(body, input, &scope, result)?;
+                }
+            }
+            Node::Partial { name, offset } => {
+                self.render_partial(name, vars, result)
+                    .map_err(|e| parser::error_at(input, *offset, e))?;
+            }
+            Node::If {
+                cond,
+                then,
+                otherwise,
+                offset,
+            } => {
+                let matched =
+                    condition(cond, vars).map_err(|e| parser::error_at(input, *offset, e))?;
+                let branch = if matched { then } else { otherwise };
+                self.render_nodes(branch, input, vars, result)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn render(&mut self, input: &str, vars: &HashMap<String, String>) -> Result<String> {
+        let nodes = parser::parse(input)?;
+        // with capacity reduces the amount of allocations you have to do
+        // by initializing the string with a set capacity by default. useful
+        // for when you are doing a ton of pushes to the string (like here)
+        let mut result = String::with_capacity(input.len());
+        self.render_nodes(&nodes, input, vars, &mut result)?;
+        Ok(result)
+    }
+}
+
 /// Find and replace keyed values in templates.
 ///
 /// Used {{ }} to avoid having to deal with the single brackets that
-/// both css and json use.
-pub fn substitute(input: &str, vars: &HashMap<String, String>) -> String {
-    // with capacity reduces the amount of allocations you have to do
-    // by initializing the string with a set capacity by default. useful
-    // for when you are doing a ton of pushes to the string (like here)
-    let mut result = String::with_capacity(input.len());
-    let mut rest = input;
-
-    // while there is text to go through, try to find {{...
-    while let Some(start) = rest.find("{{") {
-        // push the text up to the {{
-        result.push_str(&rest[..start]);
-        // store the rest of the data which comes after the {{
-        let after_open = &rest[start + 2..];
-
-        // if there is an ending }}...
-        if let Some(end) = after_open.find("}}") {
-            // the stuff in the middle is the key
-            let key = &after_open[..end];
-            // if that key exists in the valid keys hashmap...
-            if let Some(val) = vars.get(key) {
-                // push the val from the key into the result
-                result.push_str(val);
-            } else {
-                // {{ = literal {. So this prints \{\{key\}\}
-                eprintln!("warning: unknown template variable '{{{{{key}}}}}'");
-                // keep the entire invalid key for the user to debug
-                result.push_str(&rest[start..start + 2 + end + 2]);
-            }
-            // continue after the }}
-            rest = &after_open[end + 2..];
-        } else {
-            // unclosed {{ — pass through literally
-            result.push_str("{{");
-            rest = after_open;
-        }
+/// both css and json use. A key can be followed by filters, see filters.rs.
+/// {{#each colors}} / {{#each ansi}} repeat their body for every color, and
+/// {{#if mode == "light"}}...{{else}}...{{/if}} picks a branch.
+/// Errors point at the line and column of the {{ they came from.
+pub fn substitute(input: &str, vars: &HashMap<String, String>) -> Result<String> {
+    Renderer {
+        partials_dir: None,
+        active: Vec::new(),
+        strict: false,
+        errors: None,
+    }
+    .render(input, vars)
+}
+
+/// substitute, but {{> name}} pulls in partials_dir/name. used for template files.
+/// strict turns unknown variables and unterminated {{ into errors
+pub fn substitute_with_partials(
+    input: &str,
+    vars: &HashMap<String, String>,
+    partials_dir: &Path,
+    strict: bool,
+) -> Result<String> {
+    Renderer {
+        partials_dir: Some(partials_dir),
+        active: Vec::new(),
+        strict,
+        errors: None,
     }
-    result.push_str(rest);
-    result
+    .render(input, vars)
 }
 
-/// write out the substituted template into cache
-fn render_template(
-    template_path: &PathBuf,
-    out_dir: &PathBuf,
+/// read a template and substitute it
+pub fn render_template(
+    template_path: &Path,
+    partials_dir: &Path,
     vars: &HashMap<String, String>,
-) -> Result<PathBuf> {
+    strict: bool,
+) -> Result<String> {
     // get the template and read it into a string
     let input = fs::read_to_string(template_path)
         .with_context(|| format!("Failed to read template: {}", template_path.display()))?;
 
     // subsitute out the keys
-    let rendered = substitute(&input, vars);
+    substitute_with_partials(&input, vars, partials_dir, strict)
+        .with_context(|| format!("Failed to render template: {}", template_path.display()))
+}
+
+/// strictly render a template file and return every error in it instead of
+/// stopping at the first. errors in the structure of the template, like an
+/// unclosed {{#each}}, still stop it since nothing after them makes sense
+pub fn check_template(
+    template_path: &Path,
+    partials_dir: &Path,
+    vars: &HashMap<String, String>,
+) -> Result<Vec<anyhow::Error>> {
+    let input = fs::read_to_string(template_path)
+        .with_context(|| format!("Failed to read template: {}", template_path.display()))?;
+    let nodes = match parser::parse(&input) {
+        Ok(nodes) => nodes,
+        Err(e) => return Ok(vec![e]),
+    };
 
-    // create the file in the cache based on the template name
-    fs::create_dir_all(out_dir).context("Failed to create output directory")?;
-    let filename = template_path
+    let mut renderer = Renderer {
+        partials_dir: Some(partials_dir),
+        active: Vec::new(),
+        strict: true,
+        errors: Some(Vec::new()),
+    };
+    renderer.render_nodes(&nodes, &input, vars, &mut String::new())?;
+    Ok(renderer.errors.unwrap_or_default())
+}
+
+/// where a template is written: its configured outputs, or the cache dir
+/// under the template's name
+pub fn destinations(cfg: &Template, out_dir: &Path) -> Result<Vec<PathBuf>> {
+    if cfg.output.is_empty() {
+        let filename = Path::new(&cfg.name)
+            .file_name()
+            .context("Template path has no filename")?;
+        return Ok(vec![out_dir.join(filename)]);
+    }
+    cfg.output
+        .iter()
+        .map(|output| paths::expand(output))
+        .collect()
+}
+
+/// write through a temp file in the same directory and rename it into place,
+/// so an app reloading mid-write never reads half a file
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    // a symlinked config (stow, home-manager, ...) should have its target
+    // updated, renaming over it would replace the link itself
+    let path = if path.is_symlink() {
+        fs::canonicalize(path)
+            .with_context(|| format!("Failed to resolve symlink: {}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+
+    let dir = path
+        .parent()
+        .context("Output path has no parent directory")?;
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create output directory: {}", dir.display()))?;
+    let filename = path
         .file_name()
-        .context("Template path has no filename")?;
-    let out_path = out_dir.join(filename);
-    fs::write(&out_path, rendered)
-        .with_context(|| format!("Failed to write rendered template: {}", out_path.display()))?;
+        .context("Output path has no filename")?
+        .to_string_lossy();
+    let tmp = dir.join(format!(".{filename}.{}.tmp", std::process::id()));
 
-    Ok(out_path)
+    fs::write(&tmp, contents)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    if let Err(e) = fs::rename(&tmp, &path) {
+        // dont leave the temp file lying around
+        let _ = fs::remove_file(&tmp);
+        return Err(e)
+            .with_context(|| format!("Failed to write {}", path.display()));
+    }
+    Ok(())
 }
 
 /// run the reload command when given by the config
@@ -147,37 +484,96 @@ fn run_reload(command: &str, template_name: &str) {
     }
 }
 
+/// render one [[templates]] entry without writing it. gives the output paths,
+/// the rendered text and the reload command with its variables substituted
+fn render_entry(
+    cfg: &Template,
+    templates_dir: &Path,
+    partials_dir: &Path,
+    out_dir: &Path,
+    vars: &HashMap<String, String>,
+    strict: bool,
+) -> Result<(Vec<PathBuf>, String, Option<String>)> {
+    let path = templates_dir.join(&cfg.name);
+    if !path.is_file() {
+        bail!(
+            "template '{}' not found in {}",
+            cfg.name,
+            templates_dir.display()
+        );
+    }
+    let rendered = render_template(&path, partials_dir, vars, strict)?;
+    // reload commands can use the same variables, e.g. {{mode}}
+    let reload = cfg
+        .reload
+        .as_deref()
+        .map(|cmd| substitute_with_partials(cmd, vars, partials_dir, strict))
+        .transpose()
+        .with_context(|| format!("Invalid reload command for '{}'", cfg.name))?;
+    Ok((destinations(cfg, out_dir)?, rendered, reload))
+}
+
 /// render only the templates listed in config
+///
+/// every template is rendered before any of them is written. with strict set a
+/// single error fails the whole run, so a broken template never leaves the
+/// theme half applied
 pub fn render_templates(
-    palette: &[Rgb],
-    wallpaper: &Path,
+    palette: &[Swatch],
+    labels: &LabeledColors,
+    wallpaper: &str,
     templates: &[Template],
+    strict: bool,
 ) -> Result<()> {
     if templates.is_empty() {
         return Ok(());
     }
 
     let templates_dir = paths::templates_dir()?;
-    let wallpaper_str = wallpaper.to_string_lossy().to_string();
-    let vars = build_variables(palette, &wallpaper_str);
+    let vars = build_variables(palette, labels, wallpaper);
     let out_dir = paths::cache_dir()?;
+    let partials_dir = paths::partials_dir()?;
 
-    for cfg in templates {
-        let path = templates_dir.join(&cfg.name);
-        if !path.is_file() {
-            eprintln!("warning: template '{}' not found in {}", cfg.name, templates_dir.display());
-            continue;
+    let rendered: Vec<_> = templates
+        .iter()
+        .map(|cfg| {
+            let result =
+                render_entry(cfg, &templates_dir, &partials_dir, &out_dir, &vars, strict);
+            (cfg, result)
+        })
+        .collect();
+
+    if strict {
+        let errors: Vec<String> = rendered
+            .iter()
+            .filter_map(|(_, result)| result.as_ref().err())
+            .map(|e| format!("{e:#}"))
+            .collect();
+        if !errors.is_empty() {
+            bail!(
+                "strict_templates is set, so no templates were written:\n  {}",
+                errors.join("\n  ")
+            );
         }
+    }
 
-        match render_template(&path, &out_dir, &vars) {
-            Ok(out_path) => {
+    for (cfg, result) in rendered {
+        let written = result.and_then(|(outputs, rendered, reload)| {
+            for out_path in &outputs {
+                write_atomic(out_path, &rendered)?;
                 println!("Rendered template: {}", out_path.display());
-                if let Some(ref cmd) = cfg.reload {
+            }
+            Ok(reload)
+        });
+
+        match written {
+            Ok(reload) => {
+                if let Some(ref cmd) = reload {
                     run_reload(cmd, &cfg.name);
                 }
             }
             Err(e) => {
-                eprintln!("warning: {e}");
+                eprintln!("warning: {e:#}");
             }
         }
     }
@@ -201,20 +597,29 @@ mod tests {
     fn substitute_vars() {
         let vars = test_vars();
         let input = "a: {{color0}}; b: {{color1}};";
-        assert_eq!(substitute(input, &vars), "a: #ff0000; b: #00ff00;");
+        assert_eq!(substitute(input, &vars).unwrap(), "a: #ff0000; b: #00ff00;");
     }
 
     #[test]
     fn substitute_unknown_key_preserved() {
         let vars = test_vars();
-        let result = substitute("x: {{nope}};", &vars);
+        let result = substitute("x: {{nope}};", &vars).unwrap();
         assert_eq!(result, "x: {{nope}};");
     }
 
+    #[test]
+    fn env_var_names() {
+        let mut vars = test_vars();
+        vars.insert("ansi.bright_red.0x".to_string(), "0xff0000".to_string());
+        let env = env_vars(&vars);
+        assert_eq!(env[0], ("CHLOROPHYLL_ANSI_BRIGHT_RED_0X".into(), "0xff0000".into()));
+        assert_eq!(env[3].0, "CHLOROPHYLL_WALLPAPER");
+    }
+
     #[test]
     fn substitute_unclosed_brace_passthrough() {
         let vars = test_vars();
-        assert_eq!(substitute("{{color0", &vars), "{{color0");
+        assert_eq!(substitute("{{color0", &vars).unwrap(), "{{color0");
     }
 
     #[test]
@@ -226,9 +631,185 @@ mod tests {
                 color: {{color1}};
                 background-image: url("{{wallpaper}}");
             }"#;
-        let rendered = substitute(css, &vars);
+        let rendered = substitute(css, &vars).unwrap();
         assert!(rendered.contains("#ff0000"));
         assert!(rendered.contains("#00ff00"));
         assert!(rendered.contains("/home/user/wall.png"));
     }
+
+    #[test]
+    fn substitute_filters() {
+        let vars = test_vars();
+        let input = "a: {{color0 | darken 0.2}}; b: rgba({{ color1 | alpha 0.5 | rgba }});";
+        assert_eq!(
+            substitute(input, &vars).unwrap(),
+            "a: #990000; b: rgba(0,255,0,0.5);"
+        );
+    }
+
+    #[test]
+    fn substitute_unknown_filter_has_position() {
+        let vars = test_vars();
+        let err = substitute("a {\n  b: {{color0 | shiny}};\n}", &vars).unwrap_err();
+        assert_eq!(err.to_string(), "line 2, col 6: unknown filter 'shiny'");
+    }
+
+    #[test]
+    fn substitute_each_colors() {
+        let vars = test_vars();
+        let input = "{{#each colors}}\n{{@index}}={{this}}\n{{/each}}";
+        assert_eq!(substitute(input, &vars).unwrap(), "0=#ff0000\n1=#00ff00\n");
+
+        let input = "[{{#each colors}}\"{{this | strip}}\"{{#if @last}}{{else}}, {{/if}}{{/each}}]";
+        assert_eq!(
+            substitute(input, &vars).unwrap(),
+            "[\"ff0000\", \"00ff00\"]"
+        );
+    }
+
+    #[test]
+    fn substitute_if_mode() {
+        let mut vars = test_vars();
+        vars.insert("mode".to_string(), "light".to_string());
+        let input = "{{#if mode == \"light\"}}\nl\n{{else}}\nd\n{{/if}}\n";
+        assert_eq!(substitute(input, &vars).unwrap(), "l\n");
+        assert_eq!(
+            substitute("{{#if mode != 'light'}}d{{/if}}", &vars).unwrap(),
+            ""
+        );
+        assert!(substitute("{{#if nope == 'x'}}{{/if}}", &vars).is_err());
+    }
+
+    #[test]
+    fn substitute_if_quoted_operators() {
+        let mut vars = test_vars();
+        vars.insert("x".to_string(), "a==b".to_string());
+        let input = "{{#if x == \"a==b\"}}y{{else}}n{{/if}}";
+        assert_eq!(substitute(input, &vars).unwrap(), "y");
+        let input = "{{#if 'a!=b' != x}}y{{else}}n{{/if}}";
+        assert_eq!(substitute(input, &vars).unwrap(), "y");
+    }
+
+    /// a fresh dir under the system temp dir, unique per test
+    fn temp_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chlorophyll-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn substitute_partials() {
+        let dir = temp_dir(
+            "partials",
+            &[
+                ("colors.css", "{{> header}}c0: {{color0}};\n"),
+                ("header", "/* colors */\n"),
+            ],
+        );
+        let rendered =
+            substitute_with_partials("a\n{{> colors}}\nb", &test_vars(), &dir, false).unwrap();
+        assert_eq!(rendered, "a\n/* colors */\nc0: #ff0000;\nb");
+
+        let err = substitute_with_partials("{{> nope}}", &test_vars(), &dir, false).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("line 1, col 1: partial 'nope' not found")
+        );
+        assert!(substitute("{{> colors}}", &test_vars()).is_err());
+        for name in ["../colors.css", "/etc/passwd", ".."] {
+            let template = format!("{{{{> {name}}}}}");
+            let err = substitute_with_partials(&template, &test_vars(), &dir, false).unwrap_err();
+            assert!(err.to_string().contains("must be a file name"), "{err}");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn substitute_partial_cycle() {
+        let dir = temp_dir("cycle", &[("a", "{{> b}}"), ("b", "x\n{{> a}}")]);
+        let err = substitute_with_partials("{{> a}}", &test_vars(), &dir, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1, col 1: in partial 'a': line 1, col 1: in partial 'b': \
+             line 2, col 1: partial cycle: a -> b -> a"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn render_entry_substitutes_reload() {
+        let dir = temp_dir("entry", &[("colors.conf", "c0 = {{color0}}\n")]);
+        let cfg = Template {
+            name: "colors.conf".to_string(),
+            reload: Some("notify {{mode}} {{color0 | strip}}".to_string()),
+            output: vec![],
+        };
+        let mut vars = test_vars();
+        vars.insert("mode".to_string(), "dark".to_string());
+        let (outputs, rendered, reload) =
+            render_entry(&cfg, &dir, &dir.join("partials"), Path::new("/out"), &vars, true)
+                .unwrap();
+        assert_eq!(outputs, [PathBuf::from("/out/colors.conf")]);
+        assert_eq!(rendered, "c0 = #ff0000\n");
+        assert_eq!(reload.as_deref(), Some("notify dark ff0000"));
+
+        let cfg = Template {
+            reload: Some("notify {{nope}}".to_string()),
+            ..cfg
+        };
+        let err = render_entry(&cfg, &dir, &dir, Path::new("/out"), &vars, true).unwrap_err();
+        assert!(err.to_string().contains("Invalid reload command"), "{err}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn write_atomic_replaces_contents() {
+        let dir = temp_dir("atomic", &[]);
+        let out = dir.join("nested/colors.conf");
+        write_atomic(&out, "old").unwrap();
+        write_atomic(&out, "new").unwrap();
+        assert_eq!(fs::read_to_string(&out).unwrap(), "new");
+        // only the output itself is left behind, no temp files
+        assert_eq!(fs::read_dir(dir.join("nested")).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn strict_rejects_unknown_and_unclosed() {
+        let vars = test_vars();
+        let dir = Path::new("/nonexistent");
+        assert!(substitute_with_partials("{{nope}}", &vars, dir, false).is_ok());
+        let err = substitute_with_partials("a\n {{nope | strip}}", &vars, dir, true).unwrap_err();
+        assert_eq!(err.to_string(), "line 2, col 2: unknown variable 'nope'");
+
+        let err = substitute_with_partials("{{color0}} {{color1", &vars, dir, true).unwrap_err();
+        let pos = err.downcast_ref::<parser::TemplateError>().unwrap();
+        assert_eq!((pos.line, pos.col), (1, 12));
+    }
+
+    #[test]
+    fn check_collects_every_error() {
+        let dir = temp_dir(
+            "check",
+            &[(
+                "t.css",
+                "{{nope}}\n{{#each colors}}{{this | x}}{{/each}}\n{{color0}}",
+            )],
+        );
+        let errors = check_template(&dir.join("t.css"), &dir, &test_vars()).unwrap();
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 1, col 1: unknown variable 'nope'",
+                "line 2, col 17: unknown filter 'x'",
+                "line 2, col 17: unknown filter 'x'",
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
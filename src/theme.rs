@@ -3,12 +3,16 @@ use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::PathBuf;
 
-use crate::utils::{PaletteSize, cache, colorspace::Rgb, palette};
+use crate::config::ThemeConfig;
+use crate::utils::{PaletteOptions, cache, import, palette, palette::Swatch, paths};
 
 const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
 
 pub struct Theme {
-    pub wallpaper: PathBuf,
+    /// None for themes that only have an imported palette
+    pub wallpaper: Option<PathBuf>,
+    /// color scheme file the palette is imported from instead of extracted
+    scheme: Option<PathBuf>,
     use_cache: bool,
 }
 
@@ -16,33 +20,61 @@ impl Theme {
     /// create the theme being used at runtime
     pub fn new(wallpaper: PathBuf) -> Self {
         Theme {
-            wallpaper,
+            wallpaper: Some(wallpaper),
+            scheme: None,
             use_cache: true,
         }
     }
 
+    /// create a named theme from its [theme.name] config entry
+    pub fn from_config(name: &str, tc: &ThemeConfig) -> Result<Self> {
+        if tc.path.is_none() && tc.palette.is_none() {
+            bail!("Theme '{name}' needs a path, a palette or both");
+        }
+        Ok(Theme {
+            wallpaper: tc.path.as_ref().map(PathBuf::from),
+            scheme: tc.palette.as_deref().map(paths::expand).transpose()?,
+            use_cache: true,
+        })
+    }
+
     /// skip cache reads but still write on next palette extraction
     pub fn skip_cache(mut self) -> Self {
         self.use_cache = false;
         self
     }
 
-    /// sha256 of wallpaper_path + ":" + palette_size + ":" + file_contents
-    pub fn hash(&self, palette_size: PaletteSize) -> Result<String> {
-        let contents = fs::read(&self.wallpaper).context("Failed to read wallpaper file")?;
+    /// sha256 of wallpaper_path + ":" + palette_size + ":" + extraction + ":" + thumbnail_size
+    /// + ":" + coverage_weight + ":" + merge_distance + ":" + file_contents
+    pub fn hash(&self, options: &PaletteOptions) -> Result<String> {
+        let wallpaper = self.wallpaper.as_ref().context("Theme has no wallpaper")?;
+        let contents = fs::read(wallpaper).context("Failed to read wallpaper file")?;
 
         let mut hasher = Sha256::new();
-        hasher.update(self.wallpaper.to_string_lossy().as_bytes());
+        hasher.update(wallpaper.to_string_lossy().as_bytes());
+        hasher.update(b":");
+        hasher.update(options.size.get().to_string().as_bytes());
         hasher.update(b":");
-        hasher.update(palette_size.get().to_string().as_bytes());
+        hasher.update(options.extraction.as_str().as_bytes());
+        hasher.update(b":");
+        hasher.update(options.thumbnail_size.to_string().as_bytes());
+        hasher.update(b":");
+        hasher.update(options.coverage_weight.to_string().as_bytes());
+        hasher.update(b":");
+        hasher.update(options.merge_distance.to_string().as_bytes());
         hasher.update(b":");
         hasher.update(&contents);
         Ok(format!("{:x}", hasher.finalize()))
     }
 
     /// check cache, compute if miss, return scored palette (highest score first)
-    pub fn palette(&self, palette_size: PaletteSize) -> Result<Vec<Rgb>> {
-        let hash = self.hash(palette_size)?;
+    pub fn palette(&self, options: &PaletteOptions) -> Result<Vec<Swatch>> {
+        // imported schemes are quick to parse, so they skip the cache
+        if let Some(ref scheme) = self.scheme {
+            return Ok(palette::imported_palette(&import::load(scheme)?));
+        }
+
+        let hash = self.hash(options)?;
 
         if self.use_cache
             && let Some(cached) = cache::load_cache(&hash)?
@@ -50,7 +82,8 @@ impl Theme {
             return Ok(cached);
         }
 
-        let scored = palette::scored_palette(&self.wallpaper, palette_size)?;
+        let wallpaper = self.wallpaper.as_ref().context("Theme has no wallpaper")?;
+        let scored = palette::scored_palette(wallpaper, options)?;
         cache::save_cache(&hash, &scored)?;
         Ok(scored)
     }
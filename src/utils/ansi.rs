@@ -0,0 +1,244 @@
+use crate::utils::colorspace::{Hsl, Rgb};
+use crate::utils::palette::{LabeledColors, Mode, Swatch};
+
+/// the 8 terminal color slots, in ANSI order (color0 - color7).
+/// the bright variants (color8 - color15) use the same names prefixed with bright_
+pub const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// hue (0-1) each chromatic slot is anchored to, indexed like ANSI_NAMES.
+/// black and white are taken from the background and foreground labels instead
+const SLOT_HUES: [Option<f64>; 8] = [
+    None,
+    Some(0.0),
+    Some(1.0 / 3.0),
+    Some(1.0 / 6.0),
+    Some(2.0 / 3.0),
+    Some(5.0 / 6.0),
+    Some(1.0 / 2.0),
+    None,
+];
+
+/// a palette color further than this from a slot's hue (30°) doesnt fill it
+const MAX_HUE_DISTANCE: f64 = 1.0 / 12.0;
+
+/// below this saturation a color is too gray to stand in for a hue
+const MIN_SATURATION: f64 = 0.2;
+
+/// lightness range chromatic slots are pulled into so they stay readable as text
+/// on a dark background
+const DARK_LIGHTNESS_RANGE: (f64, f64) = (0.45, 0.7);
+
+/// same as DARK_LIGHTNESS_RANGE, but for text on a light background
+const LIGHT_LIGHTNESS_RANGE: (f64, f64) = (0.3, 0.5);
+
+/// how much further from the background the bright variants are
+const BRIGHT_STEP: f64 = 0.1;
+
+/// the 16 terminal colors
+pub struct AnsiColors {
+    pub normal: [Rgb; 8],
+    pub bright: [Rgb; 8],
+}
+
+/// shorter arc between two hues, hue wraps around at 1.0
+fn hue_distance(a: f64, b: f64) -> f64 {
+    let diff = (a - b).abs();
+    diff.min(1.0 - diff)
+}
+
+pub fn with_lightness(c: Rgb, lightness: f64) -> Rgb {
+    Hsl {
+        lightness,
+        ..c.hsl()
+    }
+    .to_rgb()
+}
+
+/// lightness range colors are pulled into so they read as text in this mode
+pub fn lightness_range(mode: Mode) -> (f64, f64) {
+    match mode {
+        Mode::Light => LIGHT_LIGHTNESS_RANGE,
+        _ => DARK_LIGHTNESS_RANGE,
+    }
+}
+
+/// palette colors handed out to a set of target hues
+pub struct HueMatch {
+    /// the color for each target hue, None where the palette has nothing close
+    pub colors: Vec<Option<Rgb>>,
+    /// average saturation and lightness of the usable palette colors, for
+    /// making up the missing ones so they blend in
+    pub saturation: f64,
+    pub lightness: f64,
+}
+
+/// give every target hue the palette color closest to it, each palette color
+/// going to at most one hue. `fallback` sets the look of made up colors when
+/// the palette has nothing saturated at all
+pub fn match_hues(palette: &[Swatch], hues: &[f64], fallback: Rgb) -> HueMatch {
+    let candidates: Vec<(Rgb, Hsl)> = palette
+        .iter()
+        .map(|s| (s.color, s.color.hsl()))
+        .filter(|(_, hsl)| {
+            hsl.saturation >= MIN_SATURATION && (0.1..0.9).contains(&hsl.lightness)
+        })
+        .collect();
+
+    // every (hue, candidate) pairing that is close enough, closest first
+    let mut pairs: Vec<(usize, usize, f64)> = hues
+        .iter()
+        .enumerate()
+        .flat_map(|(target, &h)| {
+            candidates
+                .iter()
+                .enumerate()
+                .map(move |(i, (_, hsl))| (target, i, hue_distance(hsl.hue, h)))
+        })
+        .filter(|&(_, _, d)| d <= MAX_HUE_DISTANCE)
+        .collect();
+    pairs.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    // greedily hand out the closest pairs so no color lands in two slots
+    let mut colors: Vec<Option<Rgb>> = vec![None; hues.len()];
+    let mut used = vec![false; candidates.len()];
+    for (target, i, _) in pairs {
+        if colors[target].is_none() && !used[i] {
+            colors[target] = Some(candidates[i].0);
+            used[i] = true;
+        }
+    }
+
+    // the look of the colors we did find, used for the ones we have to make up
+    let (saturation, lightness) = if candidates.is_empty() {
+        let hsl = fallback.hsl();
+        (hsl.saturation, hsl.lightness)
+    } else {
+        let n = candidates.len() as f64;
+        let sat = candidates.iter().map(|(_, h)| h.saturation).sum::<f64>() / n;
+        let light = candidates.iter().map(|(_, h)| h.lightness).sum::<f64>() / n;
+        (sat, light)
+    };
+
+    HueMatch {
+        colors,
+        saturation,
+        lightness,
+    }
+}
+
+/// map the palette onto the 16 terminal slots by hue
+///
+/// every chromatic slot gets the palette color closest to its hue, each palette
+/// color filling at most one slot. slots the wallpaper has no color for are
+/// synthesized at the average saturation and lightness of the colors that were
+/// found, so they blend in with the rest of the theme. black and white come
+/// from the background and foreground labels
+pub fn ansi_colors(palette: &[Swatch], labels: &LabeledColors) -> AnsiColors {
+    // in light mode text is dark, so colors are pulled darker and bright variants
+    // step down in lightness instead of up
+    let range = lightness_range(labels.mode);
+    let direction = if labels.mode == Mode::Light {
+        -1.0
+    } else {
+        1.0
+    };
+
+    let slot_hues: Vec<f64> = SLOT_HUES.iter().flatten().copied().collect();
+    let found = match_hues(palette, &slot_hues, labels.primary);
+    let mut matched = found.colors.into_iter();
+    let slots: Vec<Option<Rgb>> = SLOT_HUES
+        .iter()
+        .map(|hue| hue.and_then(|_| matched.next().flatten()))
+        .collect();
+
+    let sat = found.saturation.clamp(0.35, 0.8);
+    let light = found.lightness.clamp(range.0, range.1);
+
+    let mut normal = [Rgb(0, 0, 0); 8];
+    for (slot, hue) in SLOT_HUES.into_iter().enumerate() {
+        normal[slot] = match (hue, slots[slot]) {
+            (None, _) if slot == 0 => labels.background,
+            (None, _) => labels.foreground,
+            (Some(_), Some(c)) => {
+                let l = c.hsl().lightness;
+                with_lightness(c, l.clamp(range.0, range.1))
+            }
+            (Some(hue), None) => Hsl {
+                hue,
+                saturation: sat,
+                lightness: light,
+            }
+            .to_rgb(),
+        };
+    }
+
+    let mut bright = normal;
+    for (slot, c) in bright.iter_mut().enumerate() {
+        let l = c.hsl().lightness;
+        // bright black is the usual "dimmed text" color, so it needs a bigger step
+        let step = if slot == 0 {
+            2.5 * BRIGHT_STEP
+        } else {
+            BRIGHT_STEP
+        };
+        *c = with_lightness(*c, (l + step * direction).clamp(0.05, 0.95));
+    }
+
+    AnsiColors { normal, bright }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::palette::LabelIndices;
+
+    fn labels() -> LabeledColors {
+        LabeledColors {
+            mode: Mode::Dark,
+            background: Rgb(20, 20, 24),
+            foreground: Rgb(230, 230, 225),
+            primary: Rgb(200, 40, 40),
+            secondary: Rgb(40, 90, 200),
+            indices: LabelIndices::default(),
+        }
+    }
+
+    fn swatches(colors: &[Rgb]) -> Vec<Swatch> {
+        colors
+            .iter()
+            .map(|&color| Swatch {
+                color,
+                coverage: 1.0 / colors.len() as f64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn slots_follow_hue() {
+        let palette = swatches(&[Rgb(40, 90, 200), Rgb(200, 40, 40)]);
+        let ansi = ansi_colors(&palette, &labels());
+        assert_eq!(ansi.normal[1], Rgb(200, 40, 40));
+        assert!(hue_distance(ansi.normal[4].hsl().hue, 2.0 / 3.0) < MAX_HUE_DISTANCE);
+    }
+
+    #[test]
+    fn missing_hues_are_synthesized() {
+        let palette = swatches(&[Rgb(200, 40, 40)]);
+        let ansi = ansi_colors(&palette, &labels());
+        for (slot, hue) in SLOT_HUES.iter().enumerate() {
+            if let Some(hue) = *hue {
+                assert!(hue_distance(ansi.normal[slot].hsl().hue, hue) < 0.02);
+            }
+        }
+    }
+
+    #[test]
+    fn black_and_white_use_labels() {
+        let ansi = ansi_colors(&swatches(&[Rgb(200, 40, 40)]), &labels());
+        assert_eq!(ansi.normal[0], Rgb(20, 20, 24));
+        assert_eq!(ansi.normal[7], Rgb(230, 230, 225));
+        assert!(ansi.bright[0].hsl().lightness > ansi.normal[0].hsl().lightness);
+    }
+}
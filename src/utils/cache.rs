@@ -1,22 +1,39 @@
 use crate::utils::paths;
 use crate::utils::colorspace::Rgb;
+use crate::utils::palette::Swatch;
 use anyhow::{Context, Result};
 use std::fs;
 
 /// loads the cache for the given hash
-pub fn load_cache(hash: &str) -> Result<Option<Vec<Rgb>>> {
+pub fn load_cache(hash: &str) -> Result<Option<Vec<Swatch>>> {
     let path = paths::cache_dir()?.join(format!("{hash}.json"));
     // return none if file doesnt exist
     if !path.exists() {
         return Ok(None);
     }
     let data = fs::read_to_string(&path).context("Failed to read cache file")?;
-    let palette: Vec<Rgb> = serde_json::from_str(&data).context("Failed to parse cache file")?;
-    Ok(Some(palette))
+    match parse_cache(&data) {
+        Ok(palette) => Ok(palette),
+        Err(e) => {
+            // still a miss, the palette gets re-extracted and the file overwritten
+            eprintln!("warning: ignoring broken cache file {}: {e:#}", path.display());
+            Ok(None)
+        }
+    }
+}
+
+/// the cached swatches. caches written before swatches carried coverage are
+/// plain colors, those are a quiet miss. anything else that doesnt parse is an error
+fn parse_cache(data: &str) -> Result<Option<Vec<Swatch>>> {
+    match serde_json::from_str(data) {
+        Ok(palette) => Ok(Some(palette)),
+        Err(_) if serde_json::from_str::<Vec<Rgb>>(data).is_ok() => Ok(None),
+        Err(e) => Err(e).context("Failed to parse cache file"),
+    }
 }
 
 /// saves the cache for the given hash
-pub fn save_cache(hash: &str, palette: &[Rgb]) -> Result<()> {
+pub fn save_cache(hash: &str, palette: &[Swatch]) -> Result<()> {
     let dir = paths::cache_dir()?;
     // create the cache dir if it doesnt exist
     fs::create_dir_all(&dir).context("Failed to create cache dir")?;
@@ -33,3 +50,21 @@ pub fn clear_cache() -> Result<()> {
     println!("Cache cleared");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cache_formats() {
+        let swatches = vec![Swatch {
+            color: Rgb(1, 2, 3),
+            coverage: 0.5,
+        }];
+        let data = serde_json::to_string(&swatches).unwrap();
+        assert_eq!(parse_cache(&data).unwrap(), Some(swatches));
+        // the old format, colors without coverage
+        assert_eq!(parse_cache("[[1,2,3],[4,5,6]]").unwrap(), None);
+        assert!(parse_cache("[{\"color\": [1,2,3]").is_err());
+    }
+}
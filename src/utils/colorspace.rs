@@ -1,4 +1,6 @@
+use anyhow::{Context, bail};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Hsl {
@@ -7,14 +9,258 @@ pub struct Hsl {
     pub lightness: f64,
 }
 
+/// hue, saturation, value. same hue as Hsl, but value is the brightest channel
+/// instead of the midpoint, so full saturation + full value is the pure color
+#[derive(Debug, Clone, Copy)]
+pub struct Hsv {
+    pub hue: f64,
+    pub saturation: f64,
+    pub value: f64,
+}
+
+/// OKLab is a perceptual color space: equal distances in it look like equal
+/// differences to the eye, which raw srgb bytes dont. l is lightness in [0-1],
+/// a is green-red and b is blue-yellow, both roughly in [-0.4, 0.4].
+/// https://bottosson.github.io/posts/oklab/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Oklab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// OKLab in polar form. chroma is the distance from gray (roughly [0-0.4]) and
+/// hue the angle around it, in [0-1] like Hsl. changing only the hue here keeps
+/// the perceived lightness, which HSL doesnt
+#[derive(Debug, Clone, Copy)]
+pub struct Oklch {
+    pub l: f64,
+    pub chroma: f64,
+    pub hue: f64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Rgb(pub u8, pub u8, pub u8);
 
+/// srgb bytes are gamma encoded. undo that so the channels are proportional to light
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// re-apply the srgb gamma curve and clamp back into a byte
+fn linear_to_srgb(c: f64) -> u8 {
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// the hue shared by hsl and hsv, from channels in [0-1] and their max and chroma
+fn hue(r: f64, g: f64, b: f64, max: f64, diff: f64) -> f64 {
+    // the hue is an angle on the color wheel, but instead of 0-360 we use 0-1
+    // the logic works by determining which color is dominant, and then adjusting by the other
+    // colors.
+    // Red=0°  Yellow=60°  Green=120°  Cyan=180°  Blue=240°  Magenta=300°  Red=360°
+    // 0       1/6         2/6         3/6        4/6        5/6           1
+    // just like the unit circle minus the pi and / 2 !
+    if (max - r).abs() < 1e-10 {
+        ((g - b) / diff + if g < b { 6.0 } else { 0.0 }) / 6.0
+    } else if (max - g).abs() < 1e-10 {
+        ((b - r) / diff + 2.0) / 6.0
+    } else {
+        ((r - g) / diff + 4.0) / 6.0
+    }
+}
+
+/// the inverse of `hue`. c is the chroma and m how much every channel is lifted
+fn from_hue_chroma(hue: f64, c: f64, m: f64) -> Rgb {
+    // wrap the hue back into [0-1) so rotated hues dont need to be normalized first
+    let h = hue.rem_euclid(1.0);
+    // second largest channel, which depends on where in the 60° sector the hue is
+    let x = c * (1.0 - ((h * 6.0) % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match (h * 6.0) as u8 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    let to_byte = |v: f64| ((v + m).clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgb(to_byte(r), to_byte(g), to_byte(b))
+}
+
+/// convert a packed rgb pixel buffer (as the image crate hands it out) into OKLab
+pub fn oklab_pixels(pixels: &[u8]) -> Vec<Oklab> {
+    pixels
+        .chunks_exact(3)
+        .map(|c| Rgb(c[0], c[1], c[2]).oklab())
+        .collect()
+}
+
+impl Oklab {
+    /// back to srgb. out of gamut values are clamped per channel
+    pub fn to_rgb(self) -> Rgb {
+        // OKLab -> LMS (cube roots)
+        let l_ = self.l + 0.3963377774 * self.a + 0.2158037573 * self.b;
+        let m_ = self.l - 0.1055613458 * self.a - 0.0638541728 * self.b;
+        let s_ = self.l - 0.0894841775 * self.a - 1.2914855480 * self.b;
+
+        // undo the cube root
+        let l = l_ * l_ * l_;
+        let m = m_ * m_ * m_;
+        let s = s_ * s_ * s_;
+
+        // LMS -> linear srgb
+        let r = 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s;
+        let g = -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s;
+        let b = -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s;
+
+        Rgb(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b))
+    }
+
+    /// polar form of the same color
+    pub fn to_oklch(self) -> Oklch {
+        Oklch {
+            l: self.l,
+            chroma: (self.a * self.a + self.b * self.b).sqrt(),
+            hue: self.b.atan2(self.a).rem_euclid(std::f64::consts::TAU) / std::f64::consts::TAU,
+        }
+    }
+
+    /// euclidean distance, which in OKLab is a good perceptual delta-E.
+    /// around 0.02 is barely noticeable, above 0.1 is clearly a different color
+    pub fn distance(&self, other: &Oklab) -> f64 {
+        ((self.l - other.l).powi(2) + (self.a - other.a).powi(2) + (self.b - other.b).powi(2))
+            .sqrt()
+    }
+}
+
+impl Oklch {
+    pub fn to_oklab(self) -> Oklab {
+        let angle = self.hue * std::f64::consts::TAU;
+        Oklab {
+            l: self.l,
+            a: self.chroma * angle.cos(),
+            b: self.chroma * angle.sin(),
+        }
+    }
+
+    /// back to srgb. out of gamut values are clamped per channel
+    pub fn to_rgb(self) -> Rgb {
+        self.to_oklab().to_rgb()
+    }
+}
+
+impl Hsl {
+    /// back to srgb, the inverse of `Rgb::hsl`. hue is in [0-1] like everywhere else
+    pub fn to_rgb(self) -> Rgb {
+        let s = self.saturation.clamp(0.0, 1.0);
+        let l = self.lightness.clamp(0.0, 1.0);
+
+        // chroma is the height of the bicone at this lightness
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        // lift every channel so the midpoint lands on the lightness
+        from_hue_chroma(self.hue, c, l - c / 2.0)
+    }
+}
+
+/// parses "#rrggbb", "rrggbb", "#rgb" and "rgb". the 3 digit form repeats each
+/// digit like css does, so "#f80" is "#ff8800"
+impl FromStr for Rgb {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim();
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // from_str_radix alone would take a sign, so "+ff" would parse
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("Invalid hex color '{s}'");
+        }
+
+        let digits: Vec<u8> = match hex.len() {
+            3 => hex
+                .chars()
+                .map(|c| u8::from_str_radix(&format!("{c}{c}"), 16))
+                .collect::<Result<_, _>>(),
+            6 => (0..6)
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<_, _>>(),
+            _ => bail!("Invalid hex color '{s}': expected 3 or 6 hex digits"),
+        }
+        .with_context(|| format!("Invalid hex color '{s}'"))?;
+
+        Ok(Rgb(digits[0], digits[1], digits[2]))
+    }
+}
+
 impl Rgb {
     pub fn hex(&self) -> String {
         format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
     }
 
+    /// WCAG relative luminance in [0-1]
+    /// https://www.w3.org/TR/WCAG21/#dfn-relative-luminance
+    pub fn luminance(&self) -> f64 {
+        0.2126 * srgb_to_linear(self.0)
+            + 0.7152 * srgb_to_linear(self.1)
+            + 0.0722 * srgb_to_linear(self.2)
+    }
+
+    /// WCAG contrast ratio between two colors, from 1 (same) to 21 (black on white).
+    /// 4.5 is the usual minimum for body text
+    pub fn contrast_ratio(&self, other: &Rgb) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// black or white, whichever reads better on top of this color
+    pub fn text_color(&self) -> Rgb {
+        let (black, white) = (Rgb(0, 0, 0), Rgb(255, 255, 255));
+        if self.contrast_ratio(&black) >= self.contrast_ratio(&white) {
+            black
+        } else {
+            white
+        }
+    }
+
+    /// perceptual difference between two colors, see `Oklab::distance`
+    pub fn delta_e(&self, other: &Rgb) -> f64 {
+        self.oklab().distance(&other.oklab())
+    }
+
+    pub fn oklab(&self) -> Oklab {
+        let r = srgb_to_linear(self.0);
+        let g = srgb_to_linear(self.1);
+        let b = srgb_to_linear(self.2);
+
+        // linear srgb -> LMS cone response
+        let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
+        let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
+        let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
+
+        // the cube root is what makes the space perceptually uniform
+        let l_ = l.cbrt();
+        let m_ = m.cbrt();
+        let s_ = s.cbrt();
+
+        Oklab {
+            l: 0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_,
+            a: 1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_,
+            b: 0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_,
+        }
+    }
+
     pub fn hsl(&self) -> Hsl {
         // convert the [0-255] to [0-1]
         let r = self.0 as f64 / 255.0;
@@ -46,28 +292,139 @@ impl Rgb {
         };
 
         // calculate the hue
-        // the hue is an angle on the color wheel, but instead of 0-360 we use 0-1
-        // the logic works by determining which color is dominant, and then adjusting by the other
-        // colors.
-        // Red=0°  Yellow=60°  Green=120°  Cyan=180°  Blue=240°  Magenta=300°  Red=360°
-        // 0       1/6         2/6         3/6        4/6        5/6           1
-        // just like the unit circle minus the pi and / 2 !
-        let h = if (max - r).abs() < 1e-10 {
-            ((g - b) / diff + if g < b { 6.0 } else { 0.0 }) / 6.0
-        } else if (max - g).abs() < 1e-10 {
-            ((b - r) / diff + 2.0) / 6.0
-        } else {
-            ((r - g) / diff + 4.0) / 6.0
-        };
+        let h = hue(r, g, b, max, diff);
 
         Hsl { hue: h, saturation: s, lightness: l }
     }
+
+    pub fn hsv(&self) -> Hsv {
+        let r = self.0 as f64 / 255.0;
+        let g = self.1 as f64 / 255.0;
+        let b = self.2 as f64 / 255.0;
+
+        let max = r.max(g).max(b);
+        let diff = max - r.min(g).min(b);
+
+        // grays have no hue, and black has no saturation either
+        if diff < 1e-10 {
+            return Hsv {
+                hue: 0.0,
+                saturation: 0.0,
+                value: max,
+            };
+        }
+
+        Hsv {
+            hue: hue(r, g, b, max, diff),
+            saturation: diff / max,
+            value: max,
+        }
+    }
+
+    pub fn oklch(&self) -> Oklch {
+        self.oklab().to_oklch()
+    }
+
+    /// raise the HSL lightness by `amount` (absolute, in [0-1]), like sass' lighten()
+    pub fn lighten(&self, amount: f64) -> Rgb {
+        let hsl = self.hsl();
+        Hsl {
+            lightness: hsl.lightness + amount,
+            ..hsl
+        }
+        .to_rgb()
+    }
+
+    /// lower the HSL lightness by `amount`, like sass' darken()
+    pub fn darken(&self, amount: f64) -> Rgb {
+        self.lighten(-amount)
+    }
+
+    /// raise the HSL saturation by `amount` (absolute, in [0-1]), like sass' saturate()
+    pub fn saturate(&self, amount: f64) -> Rgb {
+        let hsl = self.hsl();
+        Hsl {
+            saturation: hsl.saturation + amount,
+            ..hsl
+        }
+        .to_rgb()
+    }
+
+    /// lower the HSL saturation by `amount`, like sass' desaturate()
+    pub fn desaturate(&self, amount: f64) -> Rgb {
+        self.saturate(-amount)
+    }
+
+    /// blend towards `other`. weight 0 is self, 1 is other. the blend happens in
+    /// OKLab, so the midpoint of two colors looks like it sits between them
+    pub fn mix(&self, other: &Rgb, weight: f64) -> Rgb {
+        let t = weight.clamp(0.0, 1.0);
+        let (a, b) = (self.oklab(), other.oklab());
+        Oklab {
+            l: a.l + (b.l - a.l) * t,
+            a: a.a + (b.a - a.a) * t,
+            b: a.b + (b.b - a.b) * t,
+        }
+        .to_rgb()
+    }
+
+    /// spin the HSL hue by `degrees`, negative goes the other way
+    pub fn rotate_hue(&self, degrees: f64) -> Rgb {
+        let hsl = self.hsl();
+        Hsl {
+            hue: hsl.hue + degrees / 360.0,
+            ..hsl
+        }
+        .to_rgb()
+    }
+
+    /// the color on the opposite side of the color wheel
+    pub fn complement(&self) -> Rgb {
+        self.rotate_hue(180.0)
+    }
+
+    /// closest color in the xterm 256 color palette, for terminals without truecolor.
+    /// only the 6x6x6 cube (16-231) and the gray ramp (232-255) are considered,
+    /// the first 16 depend on the terminal's theme
+    pub fn ansi256(&self) -> u8 {
+        // the cube steps are not even, 0 then 95 + 40n
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        let nearest = |v: u8| {
+            (0..6)
+                .min_by_key(|&i| (LEVELS[i] as i32 - v as i32).abs())
+                .unwrap_or(0)
+        };
+        let (r, g, b) = (nearest(self.0), nearest(self.1), nearest(self.2));
+        let cube = Rgb(LEVELS[r], LEVELS[g], LEVELS[b]);
+        let cube_index = (16 + 36 * r + 6 * g + b) as u8;
+
+        // grays run from 8 to 238 in steps of 10
+        let mean = (self.0 as u32 + self.1 as u32 + self.2 as u32) / 3;
+        let step = (mean.saturating_sub(3) / 10).min(23) as u8;
+        let level = 8 + step * 10;
+        let gray = Rgb(level, level, level);
+
+        if self.delta_e(&gray) < self.delta_e(&cube) {
+            232 + step
+        } else {
+            cube_index
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn ansi256_approximation() {
+        assert_eq!(Rgb(255, 0, 0).ansi256(), 196);
+        assert_eq!(Rgb(0, 0, 0).ansi256(), 16);
+        assert_eq!(Rgb(255, 255, 255).ansi256(), 231);
+        assert_eq!(Rgb(128, 128, 128).ansi256(), 244);
+        assert_eq!(Rgb(0, 95, 135).ansi256(), 24);
+    }
+
     #[test]
     fn hex_black() {
         assert_eq!(Rgb(0, 0, 0).hex(), "#000000");
@@ -93,4 +450,130 @@ mod tests {
         assert_eq!(hsl.saturation, 0.0);
         assert_eq!(hsl.lightness, 1.0);
     }
+
+    /// a 18x18x18 grid through the rgb cube, including both corners
+    fn sample_colors() -> impl Iterator<Item = Rgb> {
+        (0..=255u8).step_by(15).flat_map(|r| {
+            (0..=255u8)
+                .step_by(15)
+                .flat_map(move |g| (0..=255u8).step_by(15).map(move |b| Rgb(r, g, b)))
+        })
+    }
+
+    /// channels can be off by one from float rounding, nothing more
+    fn close(a: Rgb, b: Rgb) -> bool {
+        a.0.abs_diff(b.0) <= 1 && a.1.abs_diff(b.1) <= 1 && a.2.abs_diff(b.2) <= 1
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for c in sample_colors() {
+            assert_eq!(c.hsl().to_rgb(), c);
+        }
+    }
+
+    #[test]
+    fn hsv_components() {
+        let hsv = Rgb(255, 0, 0).hsv();
+        assert_eq!((hsv.hue, hsv.saturation, hsv.value), (0.0, 1.0, 1.0));
+        let hsv = Rgb(0, 64, 128).hsv();
+        assert!((hsv.hue - 210.0 / 360.0).abs() < 1e-3);
+        assert_eq!(hsv.saturation, 1.0);
+        assert!((hsv.value - 128.0 / 255.0).abs() < 1e-9);
+        // grays have no hue or saturation, only a value
+        let hsv = Rgb(51, 51, 51).hsv();
+        assert_eq!((hsv.hue, hsv.saturation), (0.0, 0.0));
+        assert!((hsv.value - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn oklch_round_trip() {
+        for c in sample_colors() {
+            assert_eq!(c.oklch().to_rgb(), c);
+        }
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for c in sample_colors() {
+            assert_eq!(c.hex().parse::<Rgb>().unwrap(), c);
+        }
+    }
+
+    #[test]
+    fn parse_hex_forms() {
+        assert_eq!("#f80".parse::<Rgb>().unwrap(), Rgb(255, 136, 0));
+        assert_eq!("1a2B3c".parse::<Rgb>().unwrap(), Rgb(26, 43, 60));
+        assert!("#12345".parse::<Rgb>().is_err());
+        assert!("#gg0000".parse::<Rgb>().is_err());
+        assert!("+ff000".parse::<Rgb>().is_err());
+        assert!("#+f0".parse::<Rgb>().is_err());
+    }
+
+    #[test]
+    fn lighten_darken() {
+        for c in sample_colors() {
+            assert_eq!(c.lighten(0.0), c);
+            assert!(c.lighten(0.1).hsl().lightness >= c.hsl().lightness);
+            assert!(c.darken(0.1).hsl().lightness <= c.hsl().lightness);
+        }
+        assert_eq!(Rgb(10, 10, 10).lighten(1.0), Rgb(255, 255, 255));
+        assert_eq!(Rgb(200, 30, 30).darken(1.0), Rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn saturate_desaturate() {
+        for c in sample_colors() {
+            assert!(c.saturate(0.1).hsl().saturation >= c.hsl().saturation - 1e-2);
+            assert_eq!(c.desaturate(1.0).hsl().saturation, 0.0);
+        }
+    }
+
+    #[test]
+    fn mix_endpoints() {
+        for c in sample_colors() {
+            let other = Rgb(255 - c.0, c.2, c.1);
+            assert_eq!(c.mix(&other, 0.0), c);
+            assert_eq!(c.mix(&other, 1.0), other);
+        }
+    }
+
+    #[test]
+    fn complement_twice() {
+        for c in sample_colors() {
+            assert!(close(c.complement().complement(), c));
+            assert!(close(c.rotate_hue(90.0).rotate_hue(-90.0), c));
+        }
+        assert_eq!(Rgb(255, 0, 0).complement(), Rgb(0, 255, 255));
+    }
+
+    #[test]
+    fn contrast_black_white() {
+        assert!((Rgb(0, 0, 0).contrast_ratio(&Rgb(255, 255, 255)) - 21.0).abs() < 1e-9);
+        assert_eq!(Rgb(10, 20, 30).contrast_ratio(&Rgb(10, 20, 30)), 1.0);
+        assert_eq!(Rgb(20, 20, 40).text_color(), Rgb(255, 255, 255));
+        assert_eq!(Rgb(240, 220, 120).text_color(), Rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn oklab_white() {
+        let lab = Rgb(255, 255, 255).oklab();
+        assert!((lab.l - 1.0).abs() < 1e-6);
+        assert!(lab.a.abs() < 1e-6);
+        assert!(lab.b.abs() < 1e-6);
+    }
+
+    #[test]
+    fn oklab_round_trip() {
+        let colors = [
+            Rgb(0, 0, 0),
+            Rgb(255, 0, 0),
+            Rgb(18, 52, 86),
+            Rgb(67, 67, 67),
+            Rgb(250, 240, 5),
+        ];
+        for c in colors {
+            assert_eq!(c.oklab().to_rgb(), c);
+        }
+    }
 }
@@ -0,0 +1,234 @@
+use crate::utils::ansi;
+use crate::utils::colorspace::Rgb;
+use crate::utils::palette::{LabeledColors, Swatch};
+use serde_json::json;
+
+/// file formats a palette can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// GIMP palette, also read by Inkscape and Krita
+    Gpl,
+    /// Adobe swatch exchange
+    Ase,
+    /// css custom properties on :root
+    Css,
+    /// scss variables
+    Scss,
+    /// colors, coverage and labels as json
+    Json,
+    /// X resources with the 16 terminal colors
+    Xresources,
+    /// one hex color per line
+    Txt,
+}
+
+/// every color with the name it is exported under, the palette first then the labels
+fn named_colors(palette: &[Swatch], labels: &LabeledColors) -> Vec<(String, Rgb)> {
+    palette
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (format!("color{i}"), s.color))
+        .chain([
+            ("background".to_string(), labels.background),
+            ("foreground".to_string(), labels.foreground),
+            ("primary".to_string(), labels.primary),
+            ("secondary".to_string(), labels.secondary),
+        ])
+        .collect()
+}
+
+fn gpl(name: &str, colors: &[(String, Rgb)]) -> String {
+    let mut out = format!("GIMP Palette\nName: {name}\nColumns: 8\n#\n");
+    for (label, c) in colors {
+        out.push_str(&format!("{:3} {:3} {:3}\t{label}\n", c.0, c.1, c.2));
+    }
+    out
+}
+
+/// a string as ASE stores it: a u16 length in characters, then nul terminated UTF-16BE
+fn ase_string(s: &str) -> Vec<u8> {
+    let units: Vec<u16> = s.encode_utf16().chain([0]).collect();
+    let mut out = (units.len() as u16).to_be_bytes().to_vec();
+    out.extend(units.iter().flat_map(|u| u.to_be_bytes()));
+    out
+}
+
+fn ase_block(kind: u16, body: &[u8]) -> Vec<u8> {
+    let mut out = kind.to_be_bytes().to_vec();
+    out.extend((body.len() as u32).to_be_bytes());
+    out.extend(body);
+    out
+}
+
+/// Adobe swatch exchange, every color in one group named after the theme
+/// https://www.selapa.net/swatches/colors/fileformats.php#adobe_ase
+fn ase(name: &str, colors: &[(String, Rgb)]) -> Vec<u8> {
+    const GROUP_START: u16 = 0xc001;
+    const GROUP_END: u16 = 0xc002;
+    const COLOR: u16 = 0x0001;
+    // global, spot and normal are the three color types, normal is plain rgb
+    const NORMAL: u16 = 2;
+
+    let mut out = b"ASEF".to_vec();
+    // version 1.0
+    out.extend(1u16.to_be_bytes());
+    out.extend(0u16.to_be_bytes());
+    out.extend((colors.len() as u32 + 2).to_be_bytes());
+
+    out.extend(ase_block(GROUP_START, &ase_string(name)));
+    for (label, c) in colors {
+        let mut body = ase_string(label);
+        body.extend(b"RGB ");
+        for channel in [c.0, c.1, c.2] {
+            body.extend((channel as f32 / 255.0).to_be_bytes());
+        }
+        body.extend(NORMAL.to_be_bytes());
+        out.extend(ase_block(COLOR, &body));
+    }
+    out.extend(ase_block(GROUP_END, &[]));
+    out
+}
+
+fn json(name: &str, palette: &[Swatch], labels: &LabeledColors) -> String {
+    let colors: Vec<_> = palette
+        .iter()
+        .map(|s| json!({ "hex": s.color.hex(), "coverage": s.coverage }))
+        .collect();
+    let value = json!({
+        "name": name,
+        "mode": labels.mode.as_str(),
+        "colors": colors,
+        "labels": {
+            "background": labels.background.hex(),
+            "foreground": labels.foreground.hex(),
+            "primary": labels.primary.hex(),
+            "secondary": labels.secondary.hex(),
+        },
+    });
+    // pretty printing a json! value cant fail
+    serde_json::to_string_pretty(&value).unwrap_or_default() + "\n"
+}
+
+fn xresources(name: &str, palette: &[Swatch], labels: &LabeledColors) -> String {
+    let mut out = format!("! chlorophyll: {name}\n");
+    out.push_str(&format!("*.background: {}\n", labels.background.hex()));
+    out.push_str(&format!("*.foreground: {}\n", labels.foreground.hex()));
+    out.push_str(&format!("*.cursorColor: {}\n", labels.primary.hex()));
+    let ansi = ansi::ansi_colors(palette, labels);
+    for (i, c) in ansi.normal.iter().chain(&ansi.bright).enumerate() {
+        out.push_str(&format!("*.color{i}: {}\n", c.hex()));
+    }
+    out
+}
+
+/// the palette of the theme called `name` in the given format
+pub fn export(
+    format: ExportFormat,
+    name: &str,
+    palette: &[Swatch],
+    labels: &LabeledColors,
+) -> Vec<u8> {
+    let colors = named_colors(palette, labels);
+    let text = match format {
+        ExportFormat::Ase => return ase(name, &colors),
+        ExportFormat::Gpl => gpl(name, &colors),
+        ExportFormat::Css => {
+            let vars: String = colors
+                .iter()
+                .map(|(label, c)| format!("  --{label}: {};\n", c.hex()))
+                .collect();
+            format!(":root {{\n{vars}}}\n")
+        }
+        ExportFormat::Scss => colors
+            .iter()
+            .map(|(label, c)| format!("${label}: {};\n", c.hex()))
+            .collect(),
+        ExportFormat::Json => json(name, palette, labels),
+        ExportFormat::Xresources => xresources(name, palette, labels),
+        ExportFormat::Txt => palette.iter().map(|s| s.color.hex() + "\n").collect(),
+    };
+    text.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::import;
+    use crate::utils::palette::{LabelIndices, Mode};
+
+    fn theme() -> (Vec<Swatch>, LabeledColors) {
+        let palette = vec![
+            Swatch {
+                color: Rgb(255, 0, 0),
+                coverage: 0.75,
+            },
+            Swatch {
+                color: Rgb(0, 0, 255),
+                coverage: 0.25,
+            },
+        ];
+        let labels = LabeledColors {
+            mode: Mode::Dark,
+            background: Rgb(16, 16, 16),
+            foreground: Rgb(240, 240, 240),
+            primary: Rgb(255, 0, 0),
+            secondary: Rgb(0, 0, 255),
+            indices: LabelIndices::default(),
+        };
+        (palette, labels)
+    }
+
+    fn text(format: ExportFormat) -> String {
+        let (palette, labels) = theme();
+        String::from_utf8(export(format, "test", &palette, &labels)).unwrap()
+    }
+
+    #[test]
+    fn text_formats() {
+        let gpl = text(ExportFormat::Gpl);
+        assert!(gpl.starts_with("GIMP Palette\nName: test\n"));
+        assert!(gpl.contains("255   0   0\tcolor0\n"));
+        assert!(text(ExportFormat::Css).contains("  --background: #101010;\n"));
+        assert!(text(ExportFormat::Scss).starts_with("$color0: #ff0000;\n"));
+        assert!(text(ExportFormat::Xresources).contains("*.color15: "));
+
+        let json: serde_json::Value = serde_json::from_str(&text(ExportFormat::Json)).unwrap();
+        assert_eq!(json["colors"][1]["coverage"], 0.25);
+        assert_eq!(json["labels"]["foreground"], "#f0f0f0");
+    }
+
+    #[test]
+    fn txt_and_xresources_import_back() {
+        let (palette, labels) = theme();
+        let colors: Vec<Rgb> = palette.iter().map(|s| s.color).collect();
+        assert_eq!(import::parse(&text(ExportFormat::Txt)).unwrap(), colors);
+        // color0 is the background, and repeats are dropped on import
+        let xres = import::parse(&text(ExportFormat::Xresources)).unwrap();
+        assert_eq!(xres[0], labels.background);
+        assert!(xres.len() >= 15);
+    }
+
+    #[test]
+    fn ase_layout() {
+        let (palette, labels) = theme();
+        let ase = export(ExportFormat::Ase, "test", &palette, &labels);
+        assert_eq!(&ase[..4], b"ASEF");
+        // 6 colors plus the group start and end
+        assert_eq!(u32::from_be_bytes(ase[8..12].try_into().unwrap()), 8);
+        // group start, 2 + (4 + 1) * 2 bytes of name
+        assert_eq!(&ase[12..14], &[0xc0, 0x01]);
+        assert_eq!(u32::from_be_bytes(ase[14..18].try_into().unwrap()), 12);
+        // first color: name "color0", then the model and red as a float
+        let color = &ase[30..];
+        assert_eq!(&color[..2], &[0x00, 0x01]);
+        let name_len = u16::from_be_bytes(color[6..8].try_into().unwrap()) as usize;
+        assert_eq!(name_len, 7);
+        let model = 8 + name_len * 2;
+        assert_eq!(&color[model..model + 4], b"RGB ");
+        assert_eq!(
+            f32::from_be_bytes(color[model + 4..model + 8].try_into().unwrap()),
+            1.0
+        );
+        assert_eq!(&ase[ase.len() - 6..], &[0xc0, 0x02, 0, 0, 0, 0]);
+    }
+}
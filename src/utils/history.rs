@@ -50,7 +50,7 @@ pub fn reapply_last_wallpaper(config: &Config, force: bool) -> Result<()> {
             .theme
             .get(name)
             .with_context(|| format!("Named theme '{name}' not found in config"))?;
-        let theme = Theme::new(PathBuf::from(&tc.path));
+        let theme = Theme::from_config(name, tc)?;
         let theme = if force { theme.skip_cache() } else { theme };
         change_theme(&theme, config, Some((name, tc)))?;
     } else {
@@ -0,0 +1,256 @@
+use crate::utils::colorspace::Rgb;
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// the color scheme formats a palette can be imported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// base16 or base24 YAML, base00 - base0F (base17 for base24)
+    Base16,
+    /// pywal's colors.json
+    Pywal,
+    /// *.color0: #rrggbb style X resources, #define macros included
+    Xresources,
+    /// hex colors separated by whitespace, commas or newlines
+    Hex,
+}
+
+/// `key: value` with the value unquoted. yaml comments after the value are dropped
+fn key_value(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let value = value.trim();
+    let value = match value.strip_prefix(['"', '\'']) {
+        Some(quoted) => quoted.split(['"', '\'']).next().unwrap_or_default(),
+        None => value.split_whitespace().next().unwrap_or_default(),
+    };
+    Some((key.trim(), value))
+}
+
+/// the slot a base16/base24 key names, base0A is 10
+fn base16_slot(key: &str) -> Option<usize> {
+    let digits = key.strip_prefix("base")?;
+    if digits.len() != 2 {
+        return None;
+    }
+    usize::from_str_radix(digits, 16).ok()
+}
+
+/// the last part of an X resource name, `URxvt*color4` is `color4`
+fn resource_name(key: &str) -> &str {
+    key.rsplit(['.', '*']).next().unwrap_or(key)
+}
+
+/// where an X resource lands in the palette, terminal colors first in slot order
+fn xresources_slot(key: &str) -> Option<usize> {
+    match resource_name(key) {
+        "background" => Some(256),
+        "foreground" => Some(257),
+        "cursorColor" => Some(258),
+        name => name.strip_prefix("color")?.parse().ok(),
+    }
+}
+
+fn detect(contents: &str) -> Format {
+    if contents.trim_start().starts_with('{') {
+        return Format::Pywal;
+    }
+    let keys: Vec<&str> = contents
+        .lines()
+        .filter_map(|line| Some(line.split_once(':')?.0.trim()))
+        .collect();
+    if keys.iter().any(|key| base16_slot(key).is_some()) {
+        Format::Base16
+    } else if contents
+        .lines()
+        .any(|l| l.trim_start().starts_with("#define"))
+        || keys.iter().any(|key| xresources_slot(key).is_some())
+    {
+        Format::Xresources
+    } else {
+        Format::Hex
+    }
+}
+
+fn parse_color(value: &str, line: usize) -> Result<Rgb> {
+    value
+        .parse()
+        .with_context(|| format!("line {}: '{value}' is not a hex color", line + 1))
+}
+
+/// colors keyed by their slot, in slot order
+fn in_order(mut slots: Vec<(usize, Rgb)>) -> Vec<Rgb> {
+    slots.sort_by_key(|(slot, _)| *slot);
+    slots.into_iter().map(|(_, c)| c).collect()
+}
+
+fn parse_base16(contents: &str) -> Result<Vec<Rgb>> {
+    let mut slots = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let Some((key, value)) = key_value(line) else {
+            continue;
+        };
+        if let Some(slot) = base16_slot(key) {
+            slots.push((slot, parse_color(value, i)?));
+        }
+    }
+    Ok(in_order(slots))
+}
+
+fn parse_pywal(contents: &str) -> Result<Vec<Rgb>> {
+    let json: serde_json::Value = serde_json::from_str(contents).context("Invalid JSON")?;
+    let colors = json["colors"]
+        .as_object()
+        .context("expected a \"colors\" object like pywal's colors.json")?;
+
+    let mut slots = Vec::new();
+    for (key, value) in colors {
+        let slot = key
+            .strip_prefix("color")
+            .and_then(|n| n.parse::<usize>().ok())
+            .with_context(|| format!("unexpected key '{key}' in \"colors\""))?;
+        let value = value.as_str().unwrap_or_default();
+        let color = value
+            .parse()
+            .with_context(|| format!("{key}: '{value}' is not a hex color"))?;
+        slots.push((slot, color));
+    }
+    // the special colors are usually copies of color0 and color15, but not always
+    for (slot, key) in [(256, "background"), (257, "foreground"), (258, "cursor")] {
+        if let Some(value) = json["special"][key].as_str() {
+            slots.push((slot, value.parse()?));
+        }
+    }
+    Ok(in_order(slots))
+}
+
+fn parse_xresources(contents: &str) -> Result<Vec<Rgb>> {
+    let mut defines: HashMap<&str, &str> = HashMap::new();
+    let mut slots = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if let Some(define) = line.strip_prefix("#define") {
+            if let Some((name, value)) = define.trim().split_once(char::is_whitespace) {
+                defines.insert(name, value.trim());
+            }
+            continue;
+        }
+        // ! starts a comment
+        if line.starts_with('!') {
+            continue;
+        }
+        let Some((key, value)) = key_value(line) else {
+            continue;
+        };
+        if let Some(slot) = xresources_slot(key) {
+            let value = defines.get(value).copied().unwrap_or(value);
+            slots.push((slot, parse_color(value, i)?));
+        }
+    }
+    Ok(in_order(slots))
+}
+
+fn parse_hex(contents: &str) -> Result<Vec<Rgb>> {
+    let mut colors = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        for token in line.split([',', ' ', '\t']).filter(|t| !t.is_empty()) {
+            colors.push(parse_color(token, i)?);
+        }
+    }
+    Ok(colors)
+}
+
+/// parse a color scheme into its colors, detecting the format from the contents.
+/// repeated colors are only kept the first time
+pub fn parse(contents: &str) -> Result<Vec<Rgb>> {
+    let format = detect(contents);
+    let colors = match format {
+        Format::Base16 => parse_base16(contents),
+        Format::Pywal => parse_pywal(contents),
+        Format::Xresources => parse_xresources(contents),
+        Format::Hex => parse_hex(contents),
+    }
+    .with_context(|| format!("Failed to parse {format:?} color scheme"))?;
+
+    let mut unique = Vec::new();
+    for c in colors {
+        if !unique.contains(&c) {
+            unique.push(c);
+        }
+    }
+    if unique.is_empty() {
+        bail!(
+            "No colors found. Expected base16/base24 YAML, pywal colors.json, Xresources or a list of hex colors"
+        );
+    }
+    Ok(unique)
+}
+
+/// read and parse a color scheme file
+pub fn load(path: &Path) -> Result<Vec<Rgb>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read palette {}", path.display()))?;
+    parse(&contents).with_context(|| format!("Failed to import palette {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base16_yaml() {
+        let yaml = "scheme: \"Test\"\nauthor: \"me\"\nbase01: \"282828\"\nbase00: \"181818\" # bg\n  base0F: '#a16946'\n";
+        assert_eq!(detect(yaml), Format::Base16);
+        assert_eq!(
+            parse(yaml).unwrap(),
+            vec![
+                Rgb(0x18, 0x18, 0x18),
+                Rgb(0x28, 0x28, 0x28),
+                Rgb(0xa1, 0x69, 0x46)
+            ]
+        );
+    }
+
+    #[test]
+    fn pywal_json() {
+        let json = r##"{
+            "special": {"background": "#101010", "foreground": "#eeeeee", "cursor": "#eeeeee"},
+            "colors": {"color10": "#00ff00", "color0": "#101010", "color1": "#ff0000"}
+        }"##;
+        assert_eq!(
+            parse(json).unwrap(),
+            vec![
+                Rgb(0x10, 0x10, 0x10),
+                Rgb(255, 0, 0),
+                Rgb(0, 255, 0),
+                Rgb(0xee, 0xee, 0xee)
+            ]
+        );
+    }
+
+    #[test]
+    fn xresources_with_defines() {
+        let xres = "! comment\n#define base00 #181818\n*.foreground: #d8d8d8\n*.color0: base00\nURxvt*color1:\t#ab4642\n";
+        assert_eq!(detect(xres), Format::Xresources);
+        assert_eq!(
+            parse(xres).unwrap(),
+            vec![
+                Rgb(0x18, 0x18, 0x18),
+                Rgb(0xab, 0x46, 0x42),
+                Rgb(0xd8, 0xd8, 0xd8)
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_lists_and_errors() {
+        assert_eq!(
+            parse("#ff0000, #00ff00\n\n0000ff\n").unwrap(),
+            vec![Rgb(255, 0, 0), Rgb(0, 255, 0), Rgb(0, 0, 255)]
+        );
+        let err = parse("#ff0000\nnope\n").unwrap_err();
+        assert!(format!("{err:#}").contains("line 2: 'nope' is not a hex color"));
+        assert!(parse("").is_err());
+    }
+}
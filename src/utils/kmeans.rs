@@ -0,0 +1,196 @@
+use crate::utils::colorspace::{Oklab, oklab_pixels};
+use crate::utils::palette::Swatch;
+
+/// upper bound on lloyd iterations, the centroids usually settle well before this
+const MAX_ITERATIONS: usize = 32;
+
+/// seed for the k-means++ picks. fixed so the same wallpaper always gives the same palette
+const SEED: u64 = 0x5eed_c0ff_ee00_0001;
+
+/// tiny splitmix64 generator. we only need a handful of deterministic draws
+/// for seeding, so pulling in a rand crate isnt worth it
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // top 53 bits -> [0, 1)
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn distance_sq(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// index of the centroid closest to the point
+fn nearest(point: &[f64; 3], centroids: &[[f64; 3]]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance_sq(point, a).total_cmp(&distance_sq(point, b)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// k-means++ seeding. the first centroid is drawn at random, every following one is
+/// drawn with probability proportional to its squared distance from the closest
+/// centroid so far. this spreads the seeds out, so small but distinct accents
+/// get a centroid of their own instead of being averaged into the dominant color
+fn seed_centroids(points: &[[f64; 3]], k: usize) -> Vec<[f64; 3]> {
+    let mut rng = SplitMix(SEED);
+    let first = (rng.next_f64() * points.len() as f64) as usize;
+    let mut centroids = vec![points[first.min(points.len() - 1)]];
+
+    // distance from every point to its closest centroid
+    let mut dists: Vec<f64> = points
+        .iter()
+        .map(|p| distance_sq(p, &centroids[0]))
+        .collect();
+
+    while centroids.len() < k {
+        let total: f64 = dists.iter().sum();
+        // every point sits on a centroid already, more seeds would just be duplicates
+        if total <= 0.0 {
+            break;
+        }
+
+        let mut target = rng.next_f64() * total;
+        let mut pick = points.len() - 1;
+        for (i, d) in dists.iter().enumerate() {
+            if target < *d {
+                pick = i;
+                break;
+            }
+            target -= d;
+        }
+
+        let centroid = points[pick];
+        centroids.push(centroid);
+        for (d, p) in dists.iter_mut().zip(points) {
+            *d = d.min(distance_sq(p, &centroid));
+        }
+    }
+
+    centroids
+}
+
+/// turns a pixel buffer into at most max_colors representitive colors using k-means
+///
+/// clustering happens in OKLab so that distances match how different colors look.
+/// centroids are seeded with k-means++ and then refined with lloyd's algorithm:
+/// every pixel is assigned to its nearest centroid, then each centroid moves to
+/// the mean of its pixels. this repeats until nothing moves between clusters
+pub fn quantize(pixels: &[u8], max_colors: usize) -> Vec<Swatch> {
+    let points: Vec<[f64; 3]> = oklab_pixels(pixels)
+        .into_iter()
+        .map(|p| [p.l, p.a, p.b])
+        .collect();
+
+    if points.is_empty() || max_colors == 0 {
+        return Vec::new();
+    }
+
+    let mut centroids = seed_centroids(&points, max_colors);
+    let mut assignments = vec![usize::MAX; points.len()];
+
+    for _ in 0..MAX_ITERATIONS {
+        // assignment step
+        let mut changed = false;
+        for (assigned, p) in assignments.iter_mut().zip(&points) {
+            let idx = nearest(p, &centroids);
+            if *assigned != idx {
+                *assigned = idx;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        // update step
+        let mut sums = vec![([0.0f64; 3], 0usize); centroids.len()];
+        for (&idx, p) in assignments.iter().zip(&points) {
+            let (sum, n) = &mut sums[idx];
+            sum[0] += p[0];
+            sum[1] += p[1];
+            sum[2] += p[2];
+            *n += 1;
+        }
+        for (centroid, (sum, n)) in centroids.iter_mut().zip(sums) {
+            // an empty cluster keeps its old centroid
+            if n > 0 {
+                *centroid = [sum[0] / n as f64, sum[1] / n as f64, sum[2] / n as f64];
+            }
+        }
+    }
+
+    let mut counts = vec![0usize; centroids.len()];
+    for &idx in &assignments {
+        counts[idx] += 1;
+    }
+
+    // drop centroids that ended up with no pixels
+    let total = points.len() as f64;
+    centroids
+        .iter()
+        .zip(counts)
+        .filter(|(_, n)| *n > 0)
+        .map(|(c, n)| Swatch {
+            color: Oklab {
+                l: c[0],
+                a: c[1],
+                b: c[2],
+            }
+            .to_rgb(),
+            coverage: n as f64 / total,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::colorspace::Rgb;
+
+    fn colors(swatches: &[Swatch]) -> Vec<Rgb> {
+        swatches.iter().map(|s| s.color).collect()
+    }
+
+    #[test]
+    fn uniform_pixels() {
+        let pixels: Vec<u8> = (0..10).flat_map(|_| vec![67, 67, 67]).collect();
+        let result = quantize(&pixels, 4);
+        // a single distinct color can only make one cluster
+        assert_eq!(colors(&result), vec![Rgb(67, 67, 67)]);
+    }
+
+    #[test]
+    fn respects_max_colors_limit() {
+        let pixels: Vec<u8> = (0..100).flat_map(|i| [(i * 2) as u8, 0, 0]).collect();
+        let result = quantize(&pixels, 4);
+        assert_eq!(result.len(), 4);
+    }
+
+    #[test]
+    fn keeps_small_accent() {
+        // 95 near-identical blues and 5 red pixels, the red should survive as its own color
+        let mut pixels: Vec<u8> = (0..95)
+            .flat_map(|i| [10, 20, 200 + (i % 5) as u8])
+            .collect();
+        pixels.extend((0..5).flat_map(|_| [230, 20, 20]));
+        let result = quantize(&pixels, 2);
+        let accent = result.iter().find(|s| s.color == Rgb(230, 20, 20));
+        assert!(accent.is_some_and(|s| (s.coverage - 0.05).abs() < 1e-9));
+    }
+
+    #[test]
+    fn deterministic() {
+        let pixels: Vec<u8> = (0..300).map(|i| (i * 37 % 256) as u8).collect();
+        assert_eq!(colors(&quantize(&pixels, 8)), colors(&quantize(&pixels, 8)));
+    }
+}
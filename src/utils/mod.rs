@@ -1,9 +1,16 @@
+pub mod ansi;
 pub mod cache;
 pub mod colorspace;
+pub mod export;
 pub mod history;
+pub mod import;
+pub mod kmeans;
 pub mod palette;
 pub mod paths;
 pub mod quantize;
+pub mod sequences;
+pub mod syntax;
+pub mod wu;
 
 use serde::Deserialize;
 
@@ -21,3 +28,43 @@ impl PaletteSize {
         self.0
     }
 }
+
+/// Which quantizer is used to pull the palette out of the wallpaper
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Extraction {
+    /// split the pixel set along its widest channel until there are enough boxes
+    #[default]
+    #[value(name = "median_cut")]
+    MedianCut,
+    /// cluster the pixels around k centroids, better at keeping small accents apart
+    Kmeans,
+    /// Wu's variance minimization over a color histogram. cost barely grows with
+    /// the image size, so it suits large thumbnails or full resolution
+    Wu,
+}
+
+impl Extraction {
+    /// the name used in config.toml and in the cache key
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Extraction::MedianCut => "median_cut",
+            Extraction::Kmeans => "kmeans",
+            Extraction::Wu => "wu",
+        }
+    }
+}
+
+/// Everything that changes the result of a palette extraction.
+/// All of it goes into the cache key so different settings never collide.
+#[derive(Debug, Clone, Copy)]
+pub struct PaletteOptions {
+    pub size: PaletteSize,
+    pub extraction: Extraction,
+    /// longest edge the wallpaper is shrunk to before extraction, 0 keeps full resolution
+    pub thumbnail_size: u32,
+    /// how much of the score comes from pixel coverage instead of vibrancy, in [0-1]
+    pub coverage_weight: f64,
+    /// colors closer than this OKLab delta-E are merged into one
+    pub merge_distance: f64,
+}
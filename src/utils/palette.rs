@@ -1,22 +1,115 @@
-use crate::utils::PaletteSize;
-use crate::utils::quantize;
-use crate::utils::colorspace::{Hsl, Rgb};
+use crate::utils::{Extraction, PaletteOptions, kmeans, quantize, wu};
+use crate::utils::colorspace::{Hsl, Oklab, Rgb};
 use anyhow::{Context, Result};
 use image::ImageReader;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+/// a palette color and how much of the wallpaper it stands for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Swatch {
+    pub color: Rgb,
+    /// share of the wallpaper's pixels, in [0-1], that were collapsed into this color
+    pub coverage: f64,
+}
+
+/// never ask a quantizer for more than this many times the palette size
+/// while refilling slots freed up by merging
+const MAX_OVERSAMPLE: usize = 4;
+
+/// minimum OKLab distance between primary and secondary so the two labels
+/// dont end up as shades of the same color
+const SECONDARY_MIN_DISTANCE: f64 = 0.1;
+
 struct ScoredColor {
     score: f64,
-    color: Rgb,
+    swatch: Swatch,
+}
+
+fn run_quantizer(extraction: Extraction, pixels: &[u8], max_colors: usize) -> Vec<Swatch> {
+    match extraction {
+        Extraction::MedianCut => quantize::quantize(pixels, max_colors),
+        Extraction::Kmeans => kmeans::quantize(pixels, max_colors),
+        Extraction::Wu => wu::quantize(pixels, max_colors),
+    }
+}
+
+/// quantize the pixels and merge near duplicates. if merging freed up slots, the
+/// quantizer is asked for more colors so the next best candidates fill them,
+/// until the palette is full or the image simply doesnt have more distinct colors
+fn extract(pixels: &[u8], options: &PaletteOptions) -> Vec<Swatch> {
+    let target = options.size.get();
+    let mut requested = target;
+
+    loop {
+        let raw = run_quantizer(options.extraction, pixels, requested);
+        let merged = merge_similar(raw.clone(), options.merge_distance, target);
+
+        let exhausted = raw.len() < requested || requested >= target * MAX_OVERSAMPLE;
+        if merged.len() >= target || exhausted {
+            return merged;
+        }
+        requested = (requested + target - merged.len()).min(target * MAX_OVERSAMPLE);
+    }
+}
+
+/// repeatedly merge the two closest colors while they are closer than `threshold`
+/// (in OKLab delta-E), or while there are more than `max_colors` colors left.
+/// merged colors are averaged in OKLab weighted by coverage, and their coverage adds up
+pub fn merge_similar(mut swatches: Vec<Swatch>, threshold: f64, max_colors: usize) -> Vec<Swatch> {
+    while swatches.len() > 1 {
+        // find the closest pair
+        let mut closest = (0, 1, f64::MAX);
+        for i in 0..swatches.len() {
+            for j in i + 1..swatches.len() {
+                let d = swatches[i].color.delta_e(&swatches[j].color);
+                if d < closest.2 {
+                    closest = (i, j, d);
+                }
+            }
+        }
+
+        let (i, j, d) = closest;
+        if d >= threshold && swatches.len() <= max_colors {
+            break;
+        }
+
+        let b = swatches.swap_remove(j);
+        let a = &mut swatches[i];
+        let total = a.coverage + b.coverage;
+        // fall back to an even mix when neither side has any coverage
+        let t = if total > 0.0 { b.coverage / total } else { 0.5 };
+        let (la, lb) = (a.color.oklab(), b.color.oklab());
+        a.color = Oklab {
+            l: la.l + (lb.l - la.l) * t,
+            a: la.a + (lb.a - la.a) * t,
+            b: la.b + (lb.b - la.b) * t,
+        }
+        .to_rgb();
+        a.coverage = total;
+    }
+    swatches
 }
 
 /// Score and sort a palette by a given scoring function, highest first.
-fn score_and_sort(palette: &[Rgb], scorer: impl Fn(&Hsl) -> f64) -> Vec<ScoredColor> {
+///
+/// colors that pass the scorer (score >= 0) get `coverage_weight` of their score
+/// from how much of the wallpaper they cover, relative to the biggest swatch
+fn score_and_sort(
+    palette: &[Swatch],
+    coverage_weight: f64,
+    scorer: impl Fn(&Hsl) -> f64,
+) -> Vec<ScoredColor> {
+    let max_coverage = palette.iter().map(|s| s.coverage).fold(0.0, f64::max);
     let mut scored: Vec<ScoredColor> = palette
         .iter()
-        .map(|&color| {
-            let score = scorer(&color.hsl());
-            ScoredColor { score, color }
+        .map(|&swatch| {
+            let mut score = scorer(&swatch.color.hsl());
+            if score >= 0.0 && max_coverage > 0.0 {
+                score = score * (1.0 - coverage_weight)
+                    + swatch.coverage / max_coverage * coverage_weight;
+            }
+            ScoredColor { score, swatch }
         })
         .collect();
     scored.sort_by(|a, b| b.score.total_cmp(&a.score));
@@ -24,25 +117,36 @@ fn score_and_sort(palette: &[Rgb], scorer: impl Fn(&Hsl) -> f64) -> Vec<ScoredCo
 }
 
 /// Returns palette colors sorted by vibrancy score (highest first).
-/// Uses HSL-based scoring: s^3 * (1 - |l - 0.5| * 2)
-pub fn scored_palette(path: &Path, palette_size: PaletteSize) -> Result<Vec<Rgb>> {
+/// Uses HSL-based scoring: s^3 * (1 - |l - 0.5| * 2), blended with coverage
+pub fn scored_palette(path: &Path, options: &PaletteOptions) -> Result<Vec<Swatch>> {
     let img = ImageReader::open(path)
         .context("Failed to open image")?
         .decode()
         .context("Failed to decode image")?;
 
-    // convert the image to a 128x128 thumbnail so that processing is much faster
-    let thumb = img.thumbnail(128, 128).to_rgb8();
+    // shrink the image to a thumbnail so that processing is much faster.
+    // 0 means the user wants every pixel considered
+    let thumb = match options.thumbnail_size {
+        0 => img.to_rgb8(),
+        size => img.thumbnail(size, size).to_rgb8(),
+    };
     let pixels = thumb.as_raw();
 
-    let palette = quantize::quantize(pixels, palette_size.get());
+    let palette = extract(pixels, options);
+    Ok(rank(&palette, options.coverage_weight))
+}
 
+/// sort swatches by vibrancy score, highest first
+fn rank(palette: &[Swatch], coverage_weight: f64) -> Vec<Swatch> {
     // Score each color by vibrancy
     // the equation is s^3 * (1 - |l - 0.5| * 2)
     //
     // s is cubed because we want to favor colors that are more saturated,
     // then we multiply by 1 - |l - 0.5| * 2 to favor colors that are closer to 0.5 lightness
-    let scored = score_and_sort(&palette, |hsl| {
+    //
+    // coverage_weight then trades some of that vibrancy for how much of the
+    // wallpaper the color covers, so big areas of color can outrank small accents
+    let scored = score_and_sort(palette, coverage_weight, |hsl| {
         if !(0.15..=0.85).contains(&hsl.lightness) || hsl.saturation < 0.25 {
             // filtered colors get a negative score so they sort to the end
             -1.0 + hsl.saturation * 0.01
@@ -54,7 +158,7 @@ pub fn scored_palette(path: &Path, palette_size: PaletteSize) -> Result<Vec<Rgb>
     // if every color got filtered (all scores negative), fall back to sorting by saturation
     // with a relaxed lightness filter
     let scored = if scored[0].score < 0.0 {
-        score_and_sort(&palette, |hsl| {
+        score_and_sort(palette, coverage_weight, |hsl| {
             if (0.1..0.9).contains(&hsl.lightness) {
                 hsl.saturation
             } else {
@@ -65,73 +169,303 @@ pub fn scored_palette(path: &Path, palette_size: PaletteSize) -> Result<Vec<Rgb>
         scored
     };
 
-    Ok(scored.into_iter().map(|sc| sc.color).collect())
+    scored.into_iter().map(|sc| sc.swatch).collect()
+}
+
+/// turn an imported color scheme into a palette. every color counts as the same
+/// coverage, and they are ranked like extracted colors so primary is the most vibrant
+pub fn imported_palette(colors: &[Rgb]) -> Vec<Swatch> {
+    let swatches: Vec<Swatch> = colors
+        .iter()
+        .map(|&color| Swatch {
+            color,
+            coverage: 1.0 / colors.len() as f64,
+        })
+        .collect();
+    rank(&swatches, 0.0)
+}
+
+/// relative luminance of mid gray. darker than this is a dark color, lighter is light
+const MID_LUMINANCE: f64 = 0.18;
+
+/// whether the theme is a dark UI (light text on a dark background) or the reverse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    #[default]
+    Dark,
+    Light,
+    /// pick dark or light from how bright the wallpaper is overall
+    Auto,
+}
+
+impl Mode {
+    /// the name used in config.toml and the {{mode}} template variable
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::Dark => "dark",
+            Mode::Light => "light",
+            Mode::Auto => "auto",
+        }
+    }
+}
+
+/// settings that shape how labels are picked from a palette. unlike
+/// `PaletteOptions` these are cheap to apply, so they never touch the cache.
+/// that also means switching modes reuses the cached palette
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LabelOptions {
+    pub mode: Mode,
+    /// WCAG contrast ratio foreground, primary and secondary must reach against background
+    pub min_contrast: Option<f64>,
+}
+
+/// coverage weighted mean luminance of the palette. since every pixel of the
+/// wallpaper went into exactly one swatch, this is the wallpaper's mean luminance
+pub fn mean_luminance(swatches: &[Swatch]) -> f64 {
+    let total: f64 = swatches.iter().map(|s| s.coverage).sum();
+    if total <= 0.0 {
+        // no coverage info, weigh every color the same
+        return swatches.iter().map(|s| s.color.luminance()).sum::<f64>()
+            / swatches.len().max(1) as f64;
+    }
+    swatches
+        .iter()
+        .map(|s| s.color.luminance() * s.coverage)
+        .sum::<f64>()
+        / total
 }
 
 /// struct that contains the labeled colors
 pub struct LabeledColors {
+    /// the mode the labels were picked for, never `Mode::Auto`
+    pub mode: Mode,
     pub background: Rgb,
     pub foreground: Rgb,
     pub primary: Rgb,
     pub secondary: Rgb,
+    /// the palette position each label was picked from
+    pub indices: LabelIndices,
+}
+
+/// palette positions of the labeled colors. ensure_contrast can move a label
+/// away from its palette color, the position still says where it came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LabelIndices {
+    pub background: usize,
+    pub foreground: usize,
+    pub primary: usize,
+    pub secondary: usize,
 }
 
 impl LabeledColors {
-    /// Returns the label name for a color if it matches a labeled color.
+    /// Returns the label name for the palette color at `index`, if one was picked from it.
     /// Checks in priority order: primary, secondary, background, foreground.
-    pub fn label_for(&self, color: &Rgb) -> Option<&'static str> {
+    pub fn label_for(&self, index: usize) -> Option<&'static str> {
         [
-            (&self.primary, "primary"),
-            (&self.secondary, "secondary"),
-            (&self.background, "bg"),
-            (&self.foreground, "fg"),
+            (self.indices.primary, "primary"),
+            (self.indices.secondary, "secondary"),
+            (self.indices.background, "bg"),
+            (self.indices.foreground, "fg"),
         ]
         .into_iter()
-        .find(|(c, _)| *c == color)
+        .find(|(i, _)| *i == index)
         .map(|(_, label)| label)
     }
+
+    /// each text-like label with its WCAG contrast ratio against the background
+    pub fn contrast_ratios(&self) -> [(&'static str, Rgb, f64); 3] {
+        [
+            ("foreground", self.foreground),
+            ("primary", self.primary),
+            ("secondary", self.secondary),
+        ]
+        .map(|(name, c)| (name, c, c.contrast_ratio(&self.background)))
+    }
+}
+
+/// step the lightness of `color` away from `background` until their contrast ratio
+/// reaches `target`. the OKLCH hue and chroma are kept, so the color still looks
+/// like the same hue. if the target cant be reached, the color ends up as far from
+/// the background as it can get
+pub fn ensure_contrast(color: Rgb, background: Rgb, target: f64) -> Rgb {
+    if color.contrast_ratio(&background) >= target {
+        return color;
+    }
+
+    // mid gray has the same contrast against black and white, so
+    // anything darker than that gets lighter text and vice versa
+    let lighten = background.luminance() < MID_LUMINANCE;
+    let mut lch = color.oklch();
+    let mut adjusted = color;
+    while adjusted.contrast_ratio(&background) < target {
+        let next = if lighten { lch.l + 0.01 } else { lch.l - 0.01 };
+        if !(0.0..=1.0).contains(&next) {
+            break;
+        }
+        lch.l = next;
+        adjusted = lch.to_rgb();
+    }
+    adjusted
 }
 
 // assign the actual named labels to the palette
-pub fn assign_labels(palette: &[Rgb]) -> LabeledColors {
+pub fn assign_labels(swatches: &[Swatch], options: &LabelOptions) -> LabeledColors {
+    let palette: Vec<Rgb> = swatches.iter().map(|s| s.color).collect();
     let primary = palette[0];
-    let primary_h = primary.hsl().hue;
 
-    // pick the first palette color with a sufficiently different hue from primary
-    let secondary = palette
+    // pick the first palette color that looks sufficiently different from primary
+    let secondary_index = palette
         .iter()
         .skip(1)
-        .find(|c| {
-            let diff = (c.hsl().hue - primary_h).abs();
-            // hue wraps around at 1.0, so take the shorter arc
-            let hue_dist = diff.min(1.0 - diff);
-            hue_dist > 0.05
-        })
-        .copied()
-        .unwrap_or(palette[if palette.len() > 1 { 1 } else { 0 }]);
+        .position(|c| c.delta_e(&primary) > SECONDARY_MIN_DISTANCE)
+        .map(|i| i + 1)
+        .unwrap_or(if palette.len() > 1 { 1 } else { 0 });
+
+    let mode = match options.mode {
+        Mode::Auto if mean_luminance(swatches) > MID_LUMINANCE => Mode::Light,
+        Mode::Auto => Mode::Dark,
+        mode => mode,
+    };
 
-    let bg_score = |c: &Rgb| {
+    // dark mode wants the darkest desaturated color as background and the lightest
+    // as foreground, light mode flips that around
+    let darkness = |c: &Rgb| {
         let hsl = c.hsl();
         (1.0 - hsl.lightness) * (1.0 - hsl.saturation)
     };
-    let background = *palette
-        .iter()
-        .max_by(|a, b| bg_score(a).total_cmp(&bg_score(b)))
-        .unwrap_or(&palette[0]);
-
-    let fg_score = |c: &Rgb| {
+    let lightness = |c: &Rgb| {
         let hsl = c.hsl();
         hsl.lightness * (1.0 - hsl.saturation)
     };
-    let foreground = *palette
-        .iter()
-        .max_by(|a, b| fg_score(a).total_cmp(&fg_score(b)))
-        .unwrap_or(&palette[0]);
+    let light = mode == Mode::Light;
+    let bg_score = |c: &Rgb| if light { lightness(c) } else { darkness(c) };
+    let fg_score = |c: &Rgb| if light { darkness(c) } else { lightness(c) };
+
+    let best = |score: &dyn Fn(&Rgb) -> f64| {
+        (0..palette.len())
+            .max_by(|&a, &b| score(&palette[a]).total_cmp(&score(&palette[b])))
+            .unwrap_or(0)
+    };
+    let indices = LabelIndices {
+        background: best(&bg_score),
+        foreground: best(&fg_score),
+        primary: 0,
+        secondary: secondary_index,
+    };
+    let background = palette[indices.background];
 
-    LabeledColors {
+    let mut labels = LabeledColors {
+        mode,
         background,
-        foreground,
+        foreground: palette[indices.foreground],
         primary,
-        secondary,
+        secondary: palette[indices.secondary],
+        indices,
+    };
+
+    // make sure text colors stay readable on the background
+    if let Some(target) = options.min_contrast {
+        labels.foreground = ensure_contrast(labels.foreground, background, target);
+        labels.primary = ensure_contrast(labels.primary, background, target);
+        labels.secondary = ensure_contrast(labels.secondary, background, target);
+    }
+
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swatch(color: Rgb, coverage: f64) -> Swatch {
+        Swatch { color, coverage }
+    }
+
+    #[test]
+    fn merges_near_duplicates() {
+        let swatches = vec![
+            swatch(Rgb(0, 128, 128), 0.4),
+            swatch(Rgb(0, 130, 129), 0.4),
+            swatch(Rgb(200, 30, 30), 0.2),
+        ];
+        let merged = merge_similar(swatches, 0.04, 3);
+        assert_eq!(merged.len(), 2);
+        let teal = merged.iter().find(|s| s.color.1 > 100).unwrap();
+        assert!((teal.coverage - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn keeps_distinct_colors() {
+        let swatches = vec![swatch(Rgb(0, 128, 128), 0.5), swatch(Rgb(200, 30, 30), 0.5)];
+        assert_eq!(merge_similar(swatches.clone(), 0.04, 2), swatches);
+    }
+
+    #[test]
+    fn contrast_is_enforced() {
+        let swatches = vec![
+            swatch(Rgb(70, 60, 60), 0.4),
+            swatch(Rgb(40, 40, 45), 0.4),
+            swatch(Rgb(60, 60, 66), 0.2),
+        ];
+        let options = LabelOptions {
+            mode: Mode::Dark,
+            min_contrast: Some(4.5),
+        };
+        let labels = assign_labels(&swatches, &options);
+        for (name, _, ratio) in labels.contrast_ratios() {
+            assert!(ratio >= 4.5, "{name} only reaches {ratio}");
+        }
+        // primary was lightened away from its palette color but keeps its tag
+        assert_ne!(labels.primary, swatches[0].color);
+        assert_eq!(labels.label_for(0), Some("primary"));
+        assert_eq!(labels.indices.background, 1);
+    }
+
+    #[test]
+    fn light_mode_inverts_roles() {
+        let swatches = vec![
+            swatch(Rgb(20, 20, 20), 0.3),
+            swatch(Rgb(240, 240, 240), 0.3),
+            swatch(Rgb(200, 30, 30), 0.4),
+        ];
+        let dark = assign_labels(&swatches, &LabelOptions::default());
+        let light = assign_labels(
+            &swatches,
+            &LabelOptions {
+                mode: Mode::Light,
+                min_contrast: None,
+            },
+        );
+        assert_eq!(dark.background, light.foreground);
+        assert_eq!(dark.foreground, light.background);
+    }
+
+    #[test]
+    fn auto_mode_follows_luminance() {
+        let bright = vec![
+            swatch(Rgb(230, 230, 220), 0.9),
+            swatch(Rgb(30, 30, 30), 0.1),
+        ];
+        let options = LabelOptions {
+            mode: Mode::Auto,
+            min_contrast: None,
+        };
+        assert_eq!(assign_labels(&bright, &options).mode, Mode::Light);
+        let dim = vec![
+            swatch(Rgb(230, 230, 220), 0.1),
+            swatch(Rgb(30, 30, 30), 0.9),
+        ];
+        assert_eq!(assign_labels(&dim, &options).mode, Mode::Dark);
+    }
+
+    #[test]
+    fn merges_down_to_max_colors() {
+        let swatches = vec![
+            swatch(Rgb(0, 0, 0), 0.3),
+            swatch(Rgb(255, 255, 255), 0.3),
+            swatch(Rgb(200, 30, 30), 0.4),
+        ];
+        assert_eq!(merge_similar(swatches, 0.0, 2).len(), 2);
     }
 }
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use std::path::PathBuf;
 
 /// ~/.config/chlorophyll or $XDG_CONFIG_HOME/chlorophyll
@@ -28,3 +28,87 @@ pub fn config_file() -> Result<PathBuf> {
 pub fn templates_dir() -> Result<PathBuf> {
     Ok(config_dir()?.join("templates"))
 }
+
+/// templates_dir()/partials, where {{> name}} includes are looked up
+pub fn partials_dir() -> Result<PathBuf> {
+    Ok(templates_dir()?.join("partials"))
+}
+
+/// config_dir()/palettes, where `chlorophyll import` keeps color schemes
+pub fn palettes_dir() -> Result<PathBuf> {
+    Ok(config_dir()?.join("palettes"))
+}
+
+/// expand a leading ~ and $VAR / ${VAR} in a path from the config.
+/// a variable that isnt set is an error, not an empty string, so a typo cant
+/// end up writing to /colors.conf
+pub fn expand(path: &str) -> Result<PathBuf> {
+    let mut out = String::with_capacity(path.len());
+    let mut rest = path;
+    if rest == "~" || rest.starts_with("~/") {
+        out.push_str(&std::env::var("HOME").context("HOME not set")?);
+        rest = &rest[1..];
+    }
+
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced
+                    .find('}')
+                    .with_context(|| format!("Unclosed ${{ in '{path}'"))?;
+                if end == 0 {
+                    bail!("Empty ${{}} in '{path}'");
+                }
+                (&braced[..end], end + 2)
+            }
+            None => {
+                let end = after
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+
+        if name.is_empty() {
+            // a lone $ is just a character
+            out.push('$');
+        } else {
+            let value = std::env::var(name)
+                .with_context(|| format!("Environment variable ${name} in '{path}' is not set"))?;
+            out.push_str(&value);
+        }
+        rest = &after[len..];
+    }
+    out.push_str(rest);
+    Ok(PathBuf::from(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_home_and_vars() {
+        let home = std::env::var("HOME").unwrap();
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(expand("~/a").unwrap(), PathBuf::from(format!("{home}/a")));
+        assert_eq!(
+            expand("/x/$PATH/y").unwrap(),
+            PathBuf::from(format!("/x/{path}/y"))
+        );
+        assert_eq!(
+            expand("${HOME}.d").unwrap(),
+            PathBuf::from(format!("{home}.d"))
+        );
+        assert_eq!(expand("/a/~/$").unwrap(), PathBuf::from("/a/~/$"));
+    }
+
+    #[test]
+    fn expand_missing_var_is_an_error() {
+        assert!(expand("$CHLOROPHYLL_SURELY_UNSET/x").is_err());
+        assert!(expand("${HOME").is_err());
+        assert!(expand("/a/${}/b").is_err());
+    }
+}
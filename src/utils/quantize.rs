@@ -1,24 +1,25 @@
-use crate::utils::colorspace::Rgb;
+use crate::utils::colorspace::{Oklab, Rgb, oklab_pixels};
+use crate::utils::palette::Swatch;
 
-/// a bounding box in the pixel set.
+/// a bounding box in the pixel set, in OKLab coordinates.
 /// the axis with the most variance is split next
 struct ColorBox {
-    pixels: Vec<(u8, u8, u8)>,
+    pixels: Vec<Oklab>,
     /// (min, max) for the channels across pixels in this bounding box.
-    r_range: (u8, u8),
-    g_range: (u8, u8),
-    b_range: (u8, u8),
+    l_range: (f64, f64),
+    a_range: (f64, f64),
+    b_range: (f64, f64),
 }
 
 impl ColorBox {
     /// create new bounding box set
-    fn new(pixels: Vec<(u8, u8, u8)>) -> Self {
+    fn new(pixels: Vec<Oklab>) -> Self {
         // compute channel ranges up front
-        let (r_range, g_range, b_range) = channel_ranges(&pixels);
+        let (l_range, a_range, b_range) = channel_ranges(&pixels);
         Self {
             pixels,
-            r_range,
-            g_range,
+            l_range,
+            a_range,
             b_range,
         }
     }
@@ -26,24 +27,25 @@ impl ColorBox {
     /// returns the largest span (max - min) of the channels
     /// this span is then used as the priority for choosing which box to split next.
     /// higher span = higher prio
-    /// this produces a color palette which better representsthe images color distro
-    fn widest_span(&self) -> u8 {
+    /// since OKLab is perceptual, a wide span here actually means the box holds
+    /// colors that look different, so dark gradients no longer hog the splits
+    fn widest_span(&self) -> f64 {
         // 0 = min, 1 = max
-        let r = self.r_range.1 - self.r_range.0;
-        let g = self.g_range.1 - self.g_range.0;
+        let l = self.l_range.1 - self.l_range.0;
+        let a = self.a_range.1 - self.a_range.0;
         let b = self.b_range.1 - self.b_range.0;
-        r.max(g).max(b)
+        l.max(a).max(b)
     }
 
-    /// returns 0,1,2, which correlates to r,g,b for which one has the largest
-    /// range. ties are broken in r > g > b order.
+    /// returns 0,1,2, which correlates to l,a,b for which one has the largest
+    /// range. ties are broken in l > a > b order.
     fn widest_channel(&self) -> u8 {
-        let r = self.r_range.1 - self.r_range.0;
-        let g = self.g_range.1 - self.g_range.0;
+        let l = self.l_range.1 - self.l_range.0;
+        let a = self.a_range.1 - self.a_range.0;
         let b = self.b_range.1 - self.b_range.0;
-        if r >= g && r >= b {
+        if l >= a && l >= b {
             0
-        } else if g >= b {
+        } else if a >= b {
             1
         } else {
             2
@@ -57,10 +59,10 @@ impl ColorBox {
     /// palette slots to densely populated colors
     fn split(mut self) -> (ColorBox, ColorBox) {
         let channel = self.widest_channel();
-        self.pixels.sort_by_key(|&(r, g, b)| match channel {
-            0 => r,
-            1 => g,
-            _ => b,
+        self.pixels.sort_by(|x, y| match channel {
+            0 => x.l.total_cmp(&y.l),
+            1 => x.a.total_cmp(&y.a),
+            _ => x.b.total_cmp(&y.b),
         });
 
         let mid = self.pixels.len() / 2;
@@ -70,7 +72,8 @@ impl ColorBox {
 
     /// collapses each box into a single pixel by averaging the color
     /// channels independantly. This tends to be fine because at the point that this
-    /// is called, the colors in the box are nearly the same
+    /// is called, the colors in the box are nearly the same, and averaging in OKLab
+    /// keeps the result close to what the eye would call the middle of the box
     ///
     /// perhaps a more robust solution would be to pick out the color which has the
     /// highest mode within some variance
@@ -78,26 +81,33 @@ impl ColorBox {
         if self.pixels.is_empty() {
             return Rgb(0, 0, 0);
         }
-        let (r_sum, g_sum, b_sum) =
-            self.pixels
-                .iter()
-                .fold((0u64, 0u64, 0u64), |(r, g, b), &(pr, pg, pb)| {
-                    (r + pr as u64, g + pg as u64, b + pb as u64)
-                });
-        let n = self.pixels.len() as u64;
-        Rgb((r_sum / n) as u8, (g_sum / n) as u8, (b_sum / n) as u8)
+        let (l_sum, a_sum, b_sum) = self
+            .pixels
+            .iter()
+            .fold((0.0, 0.0, 0.0), |(l, a, b), p| (l + p.l, a + p.a, b + p.b));
+        let n = self.pixels.len() as f64;
+        Oklab {
+            l: l_sum / n,
+            a: a_sum / n,
+            b: b_sum / n,
+        }
+        .to_rgb()
     }
 }
 
 /// computes the min and max values for each color channel.
-fn channel_ranges(pixels: &[(u8, u8, u8)]) -> ((u8, u8), (u8, u8), (u8, u8)) {
+fn channel_ranges(pixels: &[Oklab]) -> ((f64, f64), (f64, f64), (f64, f64)) {
     pixels.iter().fold(
-        ((u8::MAX, u8::MIN), (u8::MAX, u8::MIN), (u8::MAX, u8::MIN)),
-        |((r_min, r_max), (g_min, g_max), (b_min, b_max)), &(r, g, b)| {
+        (
+            (f64::MAX, f64::MIN),
+            (f64::MAX, f64::MIN),
+            (f64::MAX, f64::MIN),
+        ),
+        |((l_min, l_max), (a_min, a_max), (b_min, b_max)), p| {
             (
-                (r_min.min(r), r_max.max(r)),
-                (g_min.min(g), g_max.max(g)),
-                (b_min.min(b), b_max.max(b)),
+                (l_min.min(p.l), l_max.max(p.l)),
+                (a_min.min(p.a), a_max.max(p.a)),
+                (b_min.min(p.b), b_max.max(p.b)),
             )
         },
     )
@@ -106,18 +116,20 @@ fn channel_ranges(pixels: &[(u8, u8, u8)]) -> ((u8, u8), (u8, u8), (u8, u8)) {
 /// turns a pixel buffer (used by image libs) into a vec with at most max_colors
 /// entrys, which are representitive rgb values for the image
 ///
-/// the idea is that all pixels are placed into a single ColorBox, then on
-/// each iteration, the box with the widest color span is split, which produces
+/// the idea is that all pixels are converted to OKLab and placed into a single
+/// ColorBox, then on each iteration, the box with the widest color span is split, which produces
 /// 2 subsets of the box. this is repeated until we have max_colors subsets of
-/// the original box. Then compute the average color of each subset into a representitive
-pub fn quantize(pixels: &[u8], max_colors: usize) -> Vec<Rgb> {
-    let tuples: Vec<(u8, u8, u8)> = pixels.chunks_exact(3).map(|c| (c[0], c[1], c[2])).collect();
+/// the original box. Then compute the average color of each subset into a representitive,
+/// keeping the share of pixels that ended up in it
+pub fn quantize(pixels: &[u8], max_colors: usize) -> Vec<Swatch> {
+    let lab = oklab_pixels(pixels);
 
-    if tuples.is_empty() || max_colors == 0 {
+    if lab.is_empty() || max_colors == 0 {
         return Vec::new();
     }
 
-    let mut boxes: Vec<ColorBox> = vec![ColorBox::new(tuples)];
+    let total = lab.len() as f64;
+    let mut boxes: Vec<ColorBox> = vec![ColorBox::new(lab)];
 
     while boxes.len() < max_colors {
         // pick the box with the widest channel span that has at least 2 pixels
@@ -125,7 +137,7 @@ pub fn quantize(pixels: &[u8], max_colors: usize) -> Vec<Rgb> {
             .iter()
             .enumerate()
             .filter(|(_, b)| b.pixels.len() >= 2)
-            .max_by_key(|(_, b)| b.widest_span())
+            .max_by(|(_, a), (_, b)| a.widest_span().total_cmp(&b.widest_span()))
             .map(|(i, _)| i);
 
         // if no box can be split, stop early
@@ -141,7 +153,13 @@ pub fn quantize(pixels: &[u8], max_colors: usize) -> Vec<Rgb> {
     }
 
     // collapse each box into its average color and collect results into Vec.
-    boxes.iter().map(ColorBox::average).collect()
+    boxes
+        .iter()
+        .map(|b| Swatch {
+            color: b.average(),
+            coverage: b.pixels.len() as f64 / total,
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -155,10 +173,19 @@ mod tests {
         let result = quantize(&pixels, 4);
         // every box average should be the same color
         for c in &result {
-            assert_eq!(*c, Rgb(67, 67, 67));
+            assert_eq!(c.color, Rgb(67, 67, 67));
         }
     }
 
+    #[test]
+    fn coverage_sums_to_one() {
+        let mut pixels: Vec<u8> = (0..75).flat_map(|_| [200, 30, 30]).collect();
+        pixels.extend((0..25).flat_map(|_| [30, 30, 200]));
+        let result = quantize(&pixels, 2);
+        let total: f64 = result.iter().map(|s| s.coverage).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
     #[test]
     fn respects_max_colors_limit() {
         // create a gradient of distinct colors
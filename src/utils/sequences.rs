@@ -0,0 +1,154 @@
+use crate::utils::ansi::AnsiColors;
+use crate::utils::palette::LabeledColors;
+use crate::utils::paths;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+/// one OSC sequence, ended with ST instead of BEL since more terminals take it
+fn osc(code: &str, value: &str) -> String {
+    format!("\x1b]{code};{value}\x1b\\")
+}
+
+/// the escape sequences that recolor a running terminal: OSC 4 for the 16
+/// palette slots, then 10, 11 and 12 for the foreground, background and cursor
+pub fn sequences(ansi: &AnsiColors, labels: &LabeledColors) -> String {
+    let mut out = String::new();
+    for (i, c) in ansi.normal.iter().chain(&ansi.bright).enumerate() {
+        out.push_str(&osc(&format!("4;{i}"), &c.hex()));
+    }
+    out.push_str(&osc("10", &labels.foreground.hex()));
+    out.push_str(&osc("11", &labels.background.hex()));
+    out.push_str(&osc("12", &labels.primary.hex()));
+    out
+}
+
+/// every terminal in `dir` (normally /dev/pts) that belongs to `uid`
+fn user_terminals(dir: &Path, uid: u32) -> Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    let mut terminals: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        // ptmx lives here too, the terminals are the numbered ones
+        .filter(|entry| entry.file_name().to_string_lossy().parse::<u32>().is_ok())
+        .filter(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|m| m.file_type().is_char_device() && m.uid() == uid)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    terminals.sort();
+    Ok(terminals)
+}
+
+/// write the sequences to a single terminal
+fn write_sequences(terminal: &Path, sequences: &str) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(terminal)
+        .with_context(|| format!("Failed to open {}", terminal.display()))?;
+    file.write_all(sequences.as_bytes())
+        .with_context(|| format!("Failed to write to {}", terminal.display()))
+}
+
+/// recolor every terminal the user has open and save the sequences to the cache
+/// dir, so new shells can `cat ~/.cache/chlorophyll/sequences` to pick them up.
+/// returns how many terminals were recolored
+pub fn apply(ansi: &AnsiColors, labels: &LabeledColors) -> Result<usize> {
+    let sequences = sequences(ansi, labels);
+
+    let dir = paths::cache_dir()?;
+    fs::create_dir_all(&dir).context("Failed to create cache dir")?;
+    fs::write(dir.join("sequences"), &sequences).context("Failed to write sequences file")?;
+
+    // SAFETY: geteuid has no preconditions and cant fail
+    let uid = unsafe { libc::geteuid() };
+    let mut recolored = 0;
+    for terminal in user_terminals(Path::new("/dev/pts"), uid)? {
+        // a terminal closing in the meantime isnt worth failing over
+        match write_sequences(&terminal, &sequences) {
+            Ok(()) => recolored += 1,
+            Err(e) => eprintln!("warning: {e:#}"),
+        }
+    }
+    Ok(recolored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ansi;
+    use crate::utils::colorspace::Rgb;
+    use crate::utils::palette::{LabelIndices, Mode, Swatch};
+    use std::ffi::CStr;
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+
+    fn labels() -> LabeledColors {
+        LabeledColors {
+            mode: Mode::Dark,
+            background: Rgb(20, 20, 24),
+            foreground: Rgb(230, 230, 225),
+            primary: Rgb(200, 40, 40),
+            secondary: Rgb(40, 90, 200),
+            indices: LabelIndices::default(),
+        }
+    }
+
+    /// a fresh pty pair, the master to read from and the path of the terminal side
+    fn open_pty() -> (File, File, PathBuf) {
+        let (mut master, mut slave) = (0, 0);
+        let mut name = [0 as libc::c_char; 64];
+        // SAFETY: the out pointers are valid, and name has room for any pts path
+        let ret = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                name.as_mut_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(ret, 0, "openpty failed");
+        // SAFETY: openpty succeeded, so both fds are open and owned by us now
+        let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+        // SAFETY: openpty wrote a nul terminated path into name
+        let path = unsafe { CStr::from_ptr(name.as_ptr()) };
+        (master, slave, PathBuf::from(path.to_str().unwrap()))
+    }
+
+    #[test]
+    fn sequences_cover_every_slot() {
+        let labels = labels();
+        let palette = [Swatch {
+            color: Rgb(200, 40, 40),
+            coverage: 1.0,
+        }];
+        let seq = sequences(&ansi::ansi_colors(&palette, &labels), &labels);
+        assert!(seq.starts_with("\x1b]4;0;#141418\x1b\\"));
+        assert!(seq.contains("\x1b]4;15;"));
+        assert!(seq.contains("\x1b]10;#e6e6e1\x1b\\"));
+        assert!(seq.ends_with("\x1b]12;#c82828\x1b\\"));
+        assert_eq!(seq.matches("\x1b]").count(), 19);
+    }
+
+    #[test]
+    fn writes_to_a_pty() {
+        let (mut master, _slave, path) = open_pty();
+        // SAFETY: geteuid has no preconditions and cant fail
+        let uid = unsafe { libc::geteuid() };
+        if let Some(dir) = path.parent() {
+            assert!(user_terminals(dir, uid).unwrap().contains(&path));
+            assert!(!user_terminals(dir, uid + 1).unwrap().contains(&path));
+        }
+
+        let seq = "\x1b]11;#141418\x1b\\";
+        write_sequences(&path, seq).unwrap();
+        let mut buf = vec![0; seq.len()];
+        master.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, seq.as_bytes());
+    }
+}
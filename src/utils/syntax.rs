@@ -0,0 +1,136 @@
+use crate::utils::ansi;
+use crate::utils::colorspace::{Hsl, Rgb};
+use crate::utils::palette::{self, LabeledColors, Swatch};
+
+/// syntax roles picked by hue, with the hue (0-1) each is anchored to.
+/// loosely follows what most editor themes do: purple keywords, blue functions,
+/// green strings, orange numbers, yellow types
+const HUE_ROLES: [(&str, f64); 7] = [
+    ("keyword", 0.78),
+    ("function", 0.6),
+    ("string", 0.33),
+    ("number", 0.07),
+    ("type", 0.14),
+    ("constant", 0.92),
+    ("operator", 0.5),
+];
+
+/// contrast syntax colors need against the background, WCAG AA for text
+const MIN_CONTRAST: f64 = 4.5;
+
+/// comments are meant to fade into the background, but stay readable
+const COMMENT_MIN_CONTRAST: f64 = 3.0;
+
+/// colors for the syntax and ui roles of an editor theme, hue roles first
+///
+/// hue roles get the palette color closest to their hue, or one made up to
+/// match the palette like the ansi colors. the rest are the foreground and
+/// background mixed together. every text role is pushed to a readable contrast
+/// against the background
+pub fn syntax_colors(palette: &[Swatch], labels: &LabeledColors) -> Vec<(&'static str, Rgb)> {
+    let (bg, fg) = (labels.background, labels.foreground);
+    let range = ansi::lightness_range(labels.mode);
+
+    let hues: Vec<f64> = HUE_ROLES.iter().map(|(_, h)| *h).collect();
+    let found = ansi::match_hues(palette, &hues, labels.primary);
+    let sat = found.saturation.clamp(0.35, 0.8);
+    let light = found.lightness.clamp(range.0, range.1);
+
+    let mut roles: Vec<(&'static str, Rgb)> = HUE_ROLES
+        .iter()
+        .zip(found.colors)
+        .map(|(&(name, hue), c)| {
+            let c = match c {
+                Some(c) => ansi::with_lightness(c, c.hsl().lightness.clamp(range.0, range.1)),
+                None => Hsl {
+                    hue,
+                    saturation: sat,
+                    lightness: light,
+                }
+                .to_rgb(),
+            };
+            (name, palette::ensure_contrast(c, bg, MIN_CONTRAST))
+        })
+        .collect();
+
+    roles.extend([
+        ("variable", fg),
+        (
+            "punctuation",
+            palette::ensure_contrast(fg.mix(&bg, 0.25), bg, MIN_CONTRAST),
+        ),
+        (
+            "comment",
+            palette::ensure_contrast(fg.mix(&bg, 0.45), bg, COMMENT_MIN_CONTRAST),
+        ),
+        // ui surfaces, each a step further from the background
+        ("surface", bg.mix(&fg, 0.05)),
+        ("overlay", bg.mix(&fg, 0.1)),
+        ("border", bg.mix(&fg, 0.2)),
+        ("selection", bg.mix(&labels.primary, 0.3)),
+        ("cursorline", bg.mix(&fg, 0.07)),
+    ]);
+    roles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::palette::{LabelIndices, Mode};
+
+    fn labels(mode: Mode) -> LabeledColors {
+        let (background, foreground) = match mode {
+            Mode::Light => (Rgb(245, 245, 240), Rgb(30, 30, 35)),
+            _ => (Rgb(20, 20, 24), Rgb(230, 230, 225)),
+        };
+        LabeledColors {
+            mode,
+            background,
+            foreground,
+            primary: Rgb(200, 40, 40),
+            secondary: Rgb(40, 90, 200),
+            indices: LabelIndices::default(),
+        }
+    }
+
+    fn swatches(colors: &[Rgb]) -> Vec<Swatch> {
+        colors
+            .iter()
+            .map(|&color| Swatch {
+                color,
+                coverage: 1.0 / colors.len() as f64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_role_is_readable() {
+        let palette = swatches(&[Rgb(40, 90, 200), Rgb(200, 40, 40), Rgb(60, 180, 70)]);
+        for mode in [Mode::Dark, Mode::Light] {
+            let labels = labels(mode);
+            let roles = syntax_colors(&palette, &labels);
+            let names: Vec<&str> = roles.iter().map(|(name, _)| *name).collect();
+            assert_eq!(names.len(), 15);
+            assert_eq!(names[9], "comment");
+
+            for (name, c) in &roles[..9] {
+                let ratio = c.contrast_ratio(&labels.background);
+                assert!(
+                    ratio >= MIN_CONTRAST - 0.05,
+                    "{name} is {ratio:.2} in {mode:?}"
+                );
+            }
+            assert!(roles[9].1.contrast_ratio(&labels.background) >= COMMENT_MIN_CONTRAST - 0.05);
+        }
+    }
+
+    #[test]
+    fn roles_follow_hue() {
+        let palette = swatches(&[Rgb(60, 180, 70), Rgb(40, 90, 200)]);
+        let roles = syntax_colors(&palette, &labels(Mode::Dark));
+        let hue = |name: &str| roles.iter().find(|(n, _)| *n == name).unwrap().1.hsl().hue;
+        // the green swatch becomes the string color, the blue one the function color
+        assert!((hue("string") - Rgb(60, 180, 70).hsl().hue).abs() < 0.02);
+        assert!((hue("function") - Rgb(40, 90, 200).hsl().hue).abs() < 0.02);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::utils::colorspace::{Oklab, Rgb};
+use crate::utils::palette::Swatch;
+
+/// number of histogram cells per OKLab axis. index 0 is left empty as padding
+/// so the cumulative moment lookups never have to special case the lower edge
+const BINS: usize = 32;
+const SIDE: usize = BINS + 1;
+
+/// a and b of every srgb color fall inside this range, so it is safe to bin against
+const AB_MIN: f64 = -0.4;
+const AB_MAX: f64 = 0.4;
+
+/// map a channel value onto a histogram cell in 1..=BINS
+fn bin(v: f64, min: f64, max: f64) -> usize {
+    let t = ((v - min) / (max - min)).clamp(0.0, 1.0);
+    ((t * BINS as f64) as usize).min(BINS - 1) + 1
+}
+
+fn index(l: usize, a: usize, b: usize) -> usize {
+    (l * SIDE + a) * SIDE + b
+}
+
+/// cumulative statistics of the histogram. each array holds, for every cell,
+/// the sum over all cells with a lower or equal index on every axis. that lets
+/// the totals of any box be read back with 8 lookups, no matter how big it is
+struct Moments {
+    weight: Vec<f64>,
+    l: Vec<f64>,
+    a: Vec<f64>,
+    b: Vec<f64>,
+    /// sum of l^2 + a^2 + b^2, needed for the variance
+    sq: Vec<f64>,
+}
+
+/// totals over a box
+#[derive(Clone, Copy)]
+struct Sums {
+    weight: f64,
+    l: f64,
+    a: f64,
+    b: f64,
+}
+
+impl Sums {
+    /// part of the between-box variance contributed by this half of a cut
+    fn score(&self) -> f64 {
+        (self.l * self.l + self.a * self.a + self.b * self.b) / self.weight
+    }
+
+    fn minus(&self, other: &Sums) -> Sums {
+        Sums {
+            weight: self.weight - other.weight,
+            l: self.l - other.l,
+            a: self.a - other.a,
+            b: self.b - other.b,
+        }
+    }
+}
+
+/// a box in histogram space. lower bounds are exclusive, upper bounds inclusive
+#[derive(Clone, Copy)]
+struct Cube {
+    lower: [usize; 3],
+    upper: [usize; 3],
+}
+
+impl Cube {
+    /// number of histogram cells in the box. a box of one cell cant be split
+    fn cells(&self) -> usize {
+        (0..3).map(|d| self.upper[d] - self.lower[d]).product()
+    }
+}
+
+impl Moments {
+    /// bucket every pixel into the histogram, then turn it into cumulative sums.
+    /// pixels are converted one at a time so full resolution images dont need
+    /// a second copy of the whole buffer
+    fn new(pixels: &[u8]) -> Self {
+        let len = SIDE * SIDE * SIDE;
+        let mut m = Moments {
+            weight: vec![0.0; len],
+            l: vec![0.0; len],
+            a: vec![0.0; len],
+            b: vec![0.0; len],
+            sq: vec![0.0; len],
+        };
+
+        for c in pixels.chunks_exact(3) {
+            let p = Rgb(c[0], c[1], c[2]).oklab();
+            let i = index(
+                bin(p.l, 0.0, 1.0),
+                bin(p.a, AB_MIN, AB_MAX),
+                bin(p.b, AB_MIN, AB_MAX),
+            );
+            m.weight[i] += 1.0;
+            m.l[i] += p.l;
+            m.a[i] += p.a;
+            m.b[i] += p.b;
+            m.sq[i] += p.l * p.l + p.a * p.a + p.b * p.b;
+        }
+
+        for arr in [&mut m.weight, &mut m.l, &mut m.a, &mut m.b, &mut m.sq] {
+            accumulate(arr);
+        }
+        m
+    }
+
+    /// inclusion-exclusion over the 8 corners of the box
+    fn volume(arr: &[f64], cube: &Cube) -> f64 {
+        let [l0, a0, b0] = cube.lower;
+        let [l1, a1, b1] = cube.upper;
+        arr[index(l1, a1, b1)] - arr[index(l1, a1, b0)] - arr[index(l1, a0, b1)]
+            + arr[index(l1, a0, b0)]
+            - arr[index(l0, a1, b1)]
+            + arr[index(l0, a1, b0)]
+            + arr[index(l0, a0, b1)]
+            - arr[index(l0, a0, b0)]
+    }
+
+    fn sums(&self, cube: &Cube) -> Sums {
+        Sums {
+            weight: Self::volume(&self.weight, cube),
+            l: Self::volume(&self.l, cube),
+            a: Self::volume(&self.a, cube),
+            b: Self::volume(&self.b, cube),
+        }
+    }
+
+    /// sum of squared distances of the box's pixels from their mean
+    fn variance(&self, cube: &Cube) -> f64 {
+        let s = self.sums(cube);
+        if s.weight <= 0.0 {
+            return 0.0;
+        }
+        Self::volume(&self.sq, cube) - s.score()
+    }
+
+    /// find the cut along `dir` that leaves the two halves as tight as possible.
+    /// returns the cut position and its score, higher is better
+    fn maximize(&self, cube: &Cube, dir: usize, whole: &Sums) -> Option<(usize, f64)> {
+        let mut best: Option<(usize, f64)> = None;
+        for cut in cube.lower[dir] + 1..cube.upper[dir] {
+            let mut half_cube = *cube;
+            half_cube.upper[dir] = cut;
+            let half = self.sums(&half_cube);
+            let rest = whole.minus(&half);
+            // both halves need pixels, an empty half isnt a split
+            if half.weight <= 0.0 || rest.weight <= 0.0 {
+                continue;
+            }
+            let score = half.score() + rest.score();
+            if best.is_none_or(|(_, s)| score > s) {
+                best = Some((cut, score));
+            }
+        }
+        best
+    }
+
+    /// split the box in two along whichever axis reduces variance the most
+    fn cut(&self, cube: &Cube) -> Option<(Cube, Cube)> {
+        let whole = self.sums(cube);
+        let (dir, (pos, _)) = (0..3)
+            .filter_map(|dir| self.maximize(cube, dir, &whole).map(|best| (dir, best)))
+            .max_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))?;
+
+        let mut first = *cube;
+        let mut second = *cube;
+        first.upper[dir] = pos;
+        second.lower[dir] = pos;
+        Some((first, second))
+    }
+}
+
+/// in place prefix sums along each of the three axes, which together
+/// give the 3d cumulative sum
+fn accumulate(arr: &mut [f64]) {
+    for l in 1..SIDE {
+        for a in 1..SIDE {
+            for b in 1..SIDE {
+                arr[index(l, a, b)] += arr[index(l, a, b - 1)];
+            }
+        }
+    }
+    for l in 1..SIDE {
+        for a in 1..SIDE {
+            for b in 1..SIDE {
+                arr[index(l, a, b)] += arr[index(l, a - 1, b)];
+            }
+        }
+    }
+    for l in 1..SIDE {
+        for a in 1..SIDE {
+            for b in 1..SIDE {
+                arr[index(l, a, b)] += arr[index(l - 1, a, b)];
+            }
+        }
+    }
+}
+
+/// turns a pixel buffer into at most max_colors representitive colors using
+/// Wu's variance minimization
+///
+/// instead of sorting pixels like median cut does on every split, the pixels are
+/// bucketed once into a fixed OKLab histogram. the box with the highest variance
+/// is then cut wherever that minimizes the variance of the two halves. since box
+/// totals come from cumulative moments, the cost of a split doesnt depend on how
+/// many pixels there are, which is what makes large thumbnails cheap
+pub fn quantize(pixels: &[u8], max_colors: usize) -> Vec<Swatch> {
+    if pixels.len() < 3 || max_colors == 0 {
+        return Vec::new();
+    }
+
+    let moments = Moments::new(pixels);
+
+    let mut cubes = vec![Cube {
+        lower: [0; 3],
+        upper: [BINS; 3],
+    }];
+    let mut variances = vec![moments.variance(&cubes[0])];
+    let mut next = 0;
+
+    while cubes.len() < max_colors {
+        match moments.cut(&cubes[next]) {
+            Some((first, second)) => {
+                cubes[next] = first;
+                cubes.push(second);
+                variances[next] = if first.cells() > 1 {
+                    moments.variance(&first)
+                } else {
+                    0.0
+                };
+                variances.push(if second.cells() > 1 {
+                    moments.variance(&second)
+                } else {
+                    0.0
+                });
+            }
+            // this box cant be split any further, dont try it again
+            None => variances[next] = 0.0,
+        }
+
+        // pick the box with the most variance to split next
+        let Some((idx, &v)) = variances
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        else {
+            break;
+        };
+        if v <= 0.0 {
+            break;
+        }
+        next = idx;
+    }
+
+    // the mean of each box is its representitive color
+    let total = (pixels.len() / 3) as f64;
+    cubes
+        .iter()
+        .map(|cube| moments.sums(cube))
+        .filter(|s| s.weight > 0.0)
+        .map(|s| Swatch {
+            color: Oklab {
+                l: s.l / s.weight,
+                a: s.a / s.weight,
+                b: s.b / s.weight,
+            }
+            .to_rgb(),
+            coverage: s.weight / total,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_pixels() {
+        let pixels: Vec<u8> = (0..10).flat_map(|_| vec![67, 67, 67]).collect();
+        let result = quantize(&pixels, 4);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].color, Rgb(67, 67, 67));
+        assert_eq!(result[0].coverage, 1.0);
+    }
+
+    #[test]
+    fn respects_max_colors_limit() {
+        let pixels: Vec<u8> = (0..100)
+            .flat_map(|i| [(i * 2) as u8, 255 - i as u8, 0])
+            .collect();
+        let result = quantize(&pixels, 4);
+        assert_eq!(result.len(), 4);
+    }
+
+    #[test]
+    fn separates_distinct_colors() {
+        let mut pixels: Vec<u8> = (0..75).flat_map(|_| [200, 30, 30]).collect();
+        pixels.extend((0..25).flat_map(|_| [30, 30, 200]));
+        let result = quantize(&pixels, 2);
+        assert!(result.contains(&Swatch {
+            color: Rgb(200, 30, 30),
+            coverage: 0.75
+        }));
+        assert!(result.contains(&Swatch {
+            color: Rgb(30, 30, 200),
+            coverage: 0.25
+        }));
+    }
+}